@@ -0,0 +1,304 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use chrono::{NaiveDate, NaiveDateTime};
+
+use crate::sqlite::{Connection, Statement, Value};
+
+/// Describes one archived logfile in `archive_index.sqlite`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct IndexEntry {
+    /// The partition it is in.
+    pub date: NaiveDate,
+    pub first_timestamp: NaiveDateTime,
+    pub original_path: PathBuf,
+    pub size: u64,
+    /// Relative to the collection.
+    pub archived_path: PathBuf,
+}
+
+// the timestamps are kept as text that sorts in time order
+const DATE_FORMAT: &str = "%Y-%m-%d";
+const TIMESTAMP_FORMAT: &str = "%Y-%m-%d %H:%M:%S";
+
+const SCHEMA: &str = "
+    CREATE TABLE logs (
+        archived_path TEXT PRIMARY KEY,
+        date TEXT NOT NULL,
+        first_timestamp TEXT NOT NULL,
+        original_path TEXT NOT NULL,
+        size INTEGER NOT NULL
+    );
+    CREATE INDEX logs_by_first_timestamp ON logs (first_timestamp);
+";
+
+const INSERT: &str = "INSERT OR REPLACE INTO logs
+    (archived_path, date, first_timestamp, original_path, size) VALUES (?, ?, ?, ?, ?)";
+
+/// Lists every archived logfile of the collection in a single SQLite database, so that
+/// questions like which sessions there were in March can be answered without walking
+/// the partitions.
+///
+/// Kept up to date as logs are archived, compressed and removed. Whenever it is missing
+/// or damaged, it is built anew from the partitions' manifests by `rebuild`.
+#[derive(Clone)]
+pub struct ArchiveIndex {
+    collection_path: PathBuf,
+}
+
+impl ArchiveIndex {
+    pub fn new(collection_path: PathBuf) -> Self {
+        Self { collection_path }
+    }
+
+    fn index_path(&self) -> PathBuf {
+        self.collection_path.join("archive_index.sqlite")
+    }
+
+    /// Whether the index is missing or damaged, and so has to be built by `rebuild`.
+    pub fn needs_rebuild(&self) -> bool {
+        let index_path = self.index_path();
+        if !index_path.exists() {
+            return true;
+        }
+        let intact = Connection::open(&index_path).and_then(|connection| {
+            let mut check = connection.prepare("PRAGMA quick_check")?;
+            let ok = check.step()? && check.text(0) == "ok";
+            // left without its table, e.g. by a write cut short
+            Ok(ok && connection.prepare("SELECT 1 FROM logs").is_ok())
+        });
+        !intact.unwrap_or(false)
+    }
+
+    /// Adds `entry`, replacing the one with the same `archived_path`.
+    pub fn insert(&self, entry: &IndexEntry) -> io::Result<()> {
+        self.update(|connection| {
+            let mut insert = connection.prepare(INSERT)?;
+            self.execute_insert(&mut insert, entry)
+        })
+    }
+
+    /// Records that the archived logs at the first paths are now at the second ones.
+    pub fn rename(&self, renames: &[(PathBuf, PathBuf)]) -> io::Result<()> {
+        self.update(|connection| {
+            let mut rename =
+                connection.prepare("UPDATE logs SET archived_path = ? WHERE archived_path = ?")?;
+            for (from, to) in renames {
+                rename.execute(&[
+                    Value::Text(&self.relative(to).to_string_lossy()),
+                    Value::Text(&self.relative(from).to_string_lossy()),
+                ])?;
+            }
+            Ok(())
+        })
+    }
+
+    /// Drops the entries of the partitions from before `cutoff`.
+    pub fn remove_before(&self, cutoff: NaiveDate) -> io::Result<()> {
+        self.update(|connection| {
+            connection
+                .prepare("DELETE FROM logs WHERE date < ?")?
+                .execute(&[Value::Text(&cutoff.format(DATE_FORMAT).to_string())])
+        })
+    }
+
+    /// The logs that started from `from` through `to`, oldest first.
+    pub fn between(&self, from: NaiveDate, to: NaiveDate) -> io::Result<Vec<IndexEntry>> {
+        let connection = Connection::open(&self.index_path())?;
+        let mut select = connection.prepare(
+            "SELECT archived_path, date, first_timestamp, original_path, size FROM logs
+                WHERE first_timestamp >= ? AND first_timestamp < ? ORDER BY first_timestamp",
+        )?;
+        let start = from.and_hms_opt(0, 0, 0).unwrap();
+        let end = to.succ_opt().unwrap_or(to).and_hms_opt(0, 0, 0).unwrap();
+        select.bind(&[
+            Value::Text(&start.format(TIMESTAMP_FORMAT).to_string()),
+            Value::Text(&end.format(TIMESTAMP_FORMAT).to_string()),
+        ])?;
+        let invalid = |e| io::Error::new(io::ErrorKind::InvalidData, e);
+        let mut entries = vec![];
+        while select.step()? {
+            entries.push(IndexEntry {
+                archived_path: PathBuf::from(select.text(0)),
+                date: NaiveDate::parse_from_str(&select.text(1), DATE_FORMAT).map_err(invalid)?,
+                first_timestamp: NaiveDateTime::parse_from_str(&select.text(2), TIMESTAMP_FORMAT)
+                    .map_err(invalid)?,
+                original_path: PathBuf::from(select.text(3)),
+                size: select.integer(4) as u64,
+            });
+        }
+        Ok(entries)
+    }
+
+    /// Replaces the index with one of `entries`.
+    pub fn rebuild(&self, entries: &[IndexEntry]) -> io::Result<()> {
+        // never leave a half-written index behind
+        let index_path = self.index_path();
+        let tmp_path = index_path.with_extension("sqlite.tmp");
+        match fs::remove_file(&tmp_path) {
+            Err(e) if e.kind() != io::ErrorKind::NotFound => return Err(e),
+            _ => {}
+        }
+        fs::create_dir_all(&self.collection_path)?;
+        {
+            let connection = Connection::open(&tmp_path)?;
+            connection.execute_batch(SCHEMA)?;
+            connection.execute_batch("BEGIN")?;
+            let mut insert = connection.prepare(INSERT)?;
+            for entry in entries {
+                self.execute_insert(&mut insert, entry)?;
+            }
+            drop(insert);
+            connection.execute_batch("COMMIT")?;
+        }
+        fs::rename(&tmp_path, &index_path)
+    }
+
+    /// Runs `f` on the index in a transaction. An index that does not exist is left to be
+    /// built by `rebuild` from all of the collection, not just from what changes from now
+    /// on. One that cannot be updated is removed for the same reason, as it would go on
+    /// missing the change.
+    fn update(&self, f: impl FnOnce(&Connection) -> io::Result<()>) -> io::Result<()> {
+        let index_path = self.index_path();
+        if !index_path.exists() {
+            return Ok(());
+        }
+        let result = Connection::open(&index_path).and_then(|connection| {
+            connection.execute_batch("BEGIN")?;
+            f(&connection)?;
+            connection.execute_batch("COMMIT")
+        });
+        match result {
+            Err(_) => fs::remove_file(&index_path),
+            Ok(()) => Ok(()),
+        }
+    }
+
+    fn execute_insert(&self, insert: &mut Statement, entry: &IndexEntry) -> io::Result<()> {
+        insert.execute(&[
+            Value::Text(&self.relative(&entry.archived_path).to_string_lossy()),
+            Value::Text(&entry.date.format(DATE_FORMAT).to_string()),
+            Value::Text(&entry.first_timestamp.format(TIMESTAMP_FORMAT).to_string()),
+            Value::Text(&entry.original_path.to_string_lossy()),
+            Value::Integer(entry.size as i64),
+        ])
+    }
+
+    fn relative<'p>(&self, path: &'p Path) -> &'p Path {
+        path.strip_prefix(&self.collection_path).unwrap_or(path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(archived_path: &str, first_timestamp: &str) -> IndexEntry {
+        let first_timestamp =
+            NaiveDateTime::parse_from_str(first_timestamp, TIMESTAMP_FORMAT).unwrap();
+        IndexEntry {
+            date: first_timestamp.date(),
+            first_timestamp,
+            original_path: PathBuf::from(r"C:\VRChat").join(archived_path),
+            size: 1234,
+            archived_path: PathBuf::from(archived_path),
+        }
+    }
+
+    fn date(date: &str) -> NaiveDate {
+        NaiveDate::parse_from_str(date, DATE_FORMAT).unwrap()
+    }
+
+    fn archived_paths(entries: Vec<IndexEntry>) -> Vec<PathBuf> {
+        entries
+            .into_iter()
+            .map(|entry| entry.archived_path)
+            .collect()
+    }
+
+    #[test]
+    fn keeps_up_with_the_collection() {
+        let collection_path =
+            std::env::temp_dir().join(format!("archive-index-test-{}-updates", std::process::id()));
+        let _ = fs::remove_dir_all(&collection_path);
+        let index = ArchiveIndex::new(collection_path.clone());
+        assert!(index.needs_rebuild());
+        // nothing to add to until it has been built
+        index
+            .insert(&entry("2024-02/29/a.txt", "2024-02-29 23:00:00"))
+            .unwrap();
+        assert!(!index.index_path().exists());
+
+        index
+            .rebuild(&[
+                entry("2024-02/29/a.txt", "2024-02-29 23:00:00"),
+                entry("2024-03/01/b.txt", "2024-03-01 00:00:00"),
+            ])
+            .unwrap();
+        assert!(!index.needs_rebuild());
+        let mut added = entry("2024-03/31/c.txt", "2024-03-31 23:59:59");
+        added.archived_path = collection_path.join(&added.archived_path);
+        index.insert(&added).unwrap();
+        index
+            .insert(&entry("2024-04/01/d.txt", "2024-04-01 00:00:00"))
+            .unwrap();
+        assert_eq!(
+            archived_paths(
+                index
+                    .between(date("2024-03-01"), date("2024-03-31"))
+                    .unwrap()
+            ),
+            [
+                PathBuf::from("2024-03/01/b.txt"),
+                PathBuf::from("2024-03/31/c.txt")
+            ]
+        );
+        assert_eq!(
+            index
+                .between(date("2024-03-31"), date("2024-03-31"))
+                .unwrap(),
+            [entry("2024-03/31/c.txt", "2024-03-31 23:59:59")]
+        );
+
+        index
+            .rename(&[(
+                collection_path.join("2024-03/01/b.txt"),
+                collection_path.join("2024-03/01/b.txt.gz"),
+            )])
+            .unwrap();
+        index.remove_before(date("2024-03-01")).unwrap();
+        assert_eq!(
+            archived_paths(
+                index
+                    .between(date("2024-01-01"), date("2024-12-31"))
+                    .unwrap()
+            ),
+            [
+                PathBuf::from("2024-03/01/b.txt.gz"),
+                PathBuf::from("2024-03/31/c.txt"),
+                PathBuf::from("2024-04/01/d.txt")
+            ]
+        );
+        let _ = fs::remove_dir_all(&collection_path);
+    }
+
+    #[test]
+    fn needs_rebuilding_once_damaged() {
+        let collection_path =
+            std::env::temp_dir().join(format!("archive-index-test-{}-damaged", std::process::id()));
+        let _ = fs::remove_dir_all(&collection_path);
+        let index = ArchiveIndex::new(collection_path.clone());
+        index
+            .rebuild(&[entry("2024-03/01/a.txt", "2024-03-01 12:00:00")])
+            .unwrap();
+        fs::write(index.index_path(), vec![b'x'; 4096]).unwrap();
+        assert!(index.needs_rebuild());
+        // dropped instead of failing the archiving, to be built anew
+        index
+            .insert(&entry("2024-03/02/b.txt", "2024-03-02 12:00:00"))
+            .unwrap();
+        assert!(!index.index_path().exists());
+        let _ = fs::remove_dir_all(&collection_path);
+    }
+}
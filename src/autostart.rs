@@ -0,0 +1,101 @@
+use std::env;
+use std::ffi::{OsStr, OsString};
+use std::io;
+use std::iter;
+use std::mem;
+use std::os::windows::ffi::OsStrExt;
+use std::ptr;
+
+use winapi::shared::minwindef::{BYTE, DWORD, HKEY};
+use winapi::shared::winerror::{ERROR_FILE_NOT_FOUND, ERROR_SUCCESS};
+use winapi::um::{
+    winnt::{KEY_QUERY_VALUE, KEY_SET_VALUE, REG_SZ},
+    winreg::{
+        RegCloseKey, RegDeleteValueW, RegOpenKeyExW, RegQueryValueExW, RegSetValueExW,
+        HKEY_CURRENT_USER, LSTATUS,
+    },
+};
+
+use crate::APP_NAME;
+
+const RUN_KEY: &str = "Software\\Microsoft\\Windows\\CurrentVersion\\Run";
+
+fn to_wide(s: &OsStr) -> Vec<u16> {
+    s.encode_wide().chain(iter::once(0)).collect()
+}
+
+fn check(status: LSTATUS) -> io::Result<()> {
+    if status as DWORD == ERROR_SUCCESS {
+        Ok(())
+    } else {
+        Err(io::Error::from_raw_os_error(status))
+    }
+}
+
+struct RunKey(HKEY);
+
+impl RunKey {
+    fn open(access: DWORD) -> io::Result<Self> {
+        let mut hkey = ptr::null_mut();
+        let sub_key = to_wide(OsStr::new(RUN_KEY));
+        check(unsafe { RegOpenKeyExW(HKEY_CURRENT_USER, sub_key.as_ptr(), 0, access, &mut hkey) })?;
+        Ok(Self(hkey))
+    }
+}
+
+impl Drop for RunKey {
+    fn drop(&mut self) {
+        unsafe { RegCloseKey(self.0) };
+    }
+}
+
+pub fn is_enabled() -> io::Result<bool> {
+    let key = RunKey::open(KEY_QUERY_VALUE)?;
+    let name = to_wide(OsStr::new(APP_NAME));
+    let status = unsafe {
+        RegQueryValueExW(
+            key.0,
+            name.as_ptr(),
+            ptr::null_mut(),
+            ptr::null_mut(),
+            ptr::null_mut(),
+            ptr::null_mut(),
+        )
+    };
+    match status as DWORD {
+        ERROR_FILE_NOT_FOUND => Ok(false),
+        _ => check(status).map(|_| true),
+    }
+}
+
+pub fn enable() -> io::Result<()> {
+    let mut command = OsString::from("\"");
+    command.push(env::current_exe()?);
+    command.push("\"");
+    if crate::PORTABLE_PATH.is_some() {
+        command.push(" --portable");
+    }
+    let data = to_wide(&command);
+    let key = RunKey::open(KEY_SET_VALUE)?;
+    let name = to_wide(OsStr::new(APP_NAME));
+    check(unsafe {
+        RegSetValueExW(
+            key.0,
+            name.as_ptr(),
+            0,
+            REG_SZ,
+            data.as_ptr() as *const BYTE,
+            (data.len() * mem::size_of::<u16>()) as DWORD,
+        )
+    })
+}
+
+pub fn disable() -> io::Result<()> {
+    let key = RunKey::open(KEY_SET_VALUE)?;
+    let name = to_wide(OsStr::new(APP_NAME));
+    let status = unsafe { RegDeleteValueW(key.0, name.as_ptr()) };
+    match status as DWORD {
+        ERROR_FILE_NOT_FOUND => Ok(()),
+        _ => check(status),
+    }
+}
@@ -0,0 +1,461 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+use chrono::{FixedOffset, NaiveTime};
+use regex::bytes::{Captures, Regex};
+use regex::RegexSet;
+use serde::{Deserialize, Serialize};
+
+use crate::locale::Locale;
+use crate::partition::{self, PartitionTemplate};
+use crate::{LinkMode, NotificationLevel, PartitionDate};
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub interval_secs: u64,
+    /// Used instead of `interval_secs` while VRChat is not running, 0 to always use that.
+    pub idle_interval_secs: u64,
+    pub collection_path: Option<PathBuf>,
+    pub vrchat_log_dir: Option<PathBuf>,
+    /// Takes precedence over `vrchat_log_dir` when non-empty.
+    pub vrchat_log_dirs: Vec<PathBuf>,
+    pub link_mode: LinkMode,
+    pub watch: bool,
+    pub locale: Option<Locale>,
+    pub notify_on_archive: bool,
+    pub retention_days: Option<u32>,
+    pub compress_after_days: Option<u32>,
+    /// Merges logs of a day that follow one another within this many seconds into one
+    /// extra file, leaving the logs themselves alone.
+    pub merge_gap_secs: Option<u64>,
+    pub partition_date: PartitionDate,
+    /// Files logs by their date at this UTC offset instead of the system time zone.
+    pub utc_offset_minutes: Option<i32>,
+    /// Skips logfiles whose content is already somewhere in the collection.
+    pub dedupe: bool,
+    /// Keeps `archive_index.sqlite` in the collection, listing every archived log for
+    /// `--sessions`.
+    pub archive_index: bool,
+    /// Only archives logfiles with a line matching this regex.
+    pub content_filter: Option<String>,
+    /// How far into a log `content_filter` and `world_folders` look, by default to the end.
+    pub scan_max_bytes: Option<u64>,
+    /// Never archives logfiles whose name matches any of these regexes.
+    pub exclude: Vec<String>,
+    /// Replaces the regex that tells logfiles by their name. Read at startup only.
+    pub filename_pattern: Option<String>,
+    /// Replaces the regex that finds the header's timestamp, which has to capture
+    /// `yyyy`, `MM`, `dd`, `hh`, `mm` and `ss`, and may capture the date separators as
+    /// `sep1` and `sep2` to have them match. Read at startup only.
+    pub header_pattern: Option<String>,
+    pub notification_level: NotificationLevel,
+    pub max_notifications_per_minute: u32,
+    /// Holds back an error identical to one notified this recently, even across restarts.
+    pub notification_cooldown_secs: u64,
+    /// Shows no balloons within this time of day by the local clock, e.g. `22:00-07:00`.
+    pub quiet_hours: Option<String>,
+    /// Shows the balloons held back during quiet hours once they are over, instead of
+    /// dropping them.
+    pub quiet_hours_hold: bool,
+    /// Still tells about a crash during quiet hours.
+    pub quiet_hours_crashes: bool,
+    /// Asks before exiting from the tray menu.
+    pub confirm_exit: bool,
+    /// Shows how many logs were archived when exiting from the tray menu.
+    pub exit_summary: bool,
+    /// Pins the collection to Explorer's Quick Access once.
+    pub pin_to_quick_access: bool,
+    /// Looks for a new release on GitHub once a day.
+    pub check_for_updates: bool,
+    /// Where each day's logs go within the collection, see `PartitionTemplate`, e.g.
+    /// `{yyyy}/{yyyy}-{MM}/{dd}` to group months by year. Partitions already in another
+    /// layout stay where they are, leaving the collection with a mix of both.
+    pub partition_template: String,
+    /// Scans log headers through a memory mapping instead of a read.
+    pub mmap_headers: bool,
+    /// Also archives the logs already there at startup, rather than only newer ones.
+    pub archive_existing: bool,
+    /// Leaves a logfile alone until it has gone this long without being written to.
+    pub min_age_secs: u64,
+    /// In Move mode, copies rather than moves this many of the newest logs in each
+    /// VRChat folder, so they stay there for other tools.
+    pub keep_recent: usize,
+    /// Pauses Copy and Move mode while the collection's drive has less than this free, and
+    /// otherwise warns if VRChat's does. 0 to never check.
+    pub min_free_space_mb: u64,
+    /// Turns on NTFS compression for new partition folders, which the logs inherit.
+    pub ntfs_compression: bool,
+    /// Names copied or moved logs after their first timestamp, e.g.
+    /// `2024-01-02_03-04-05.txt`, rather than keeping VRChat's name.
+    pub timestamp_names: bool,
+    /// Files logs into a folder named after the first world joined, within their day.
+    pub world_folders: bool,
+    /// Also hard links every archived log into a single `_flat` folder in the collection,
+    /// named `YYYY-MM-DD_<name>`.
+    pub flat_mirror: bool,
+    /// Also archives the crash dumps VRChat leaves behind, next to the logs of the day.
+    pub crash_dumps: bool,
+    /// Where the crash dumps are looked for, by default `%TEMP%\VRChat\VRChat\Crashes`.
+    pub crash_dump_dir: Option<PathBuf>,
+}
+
+// matches both the legacy `output_log_HH-MM-SS.txt` and
+// the current `output_log_YYYY-MM-DD_HH-MM-SS.txt` naming
+const DEFAULT_FILENAME_PATTERN: &str =
+    "^output_log_(\\d{2}-\\d{2}-\\d{2}|\\d{4}-\\d{2}-\\d{2}_\\d{2}-\\d{2}-\\d{2})\\.txt$";
+// `YYYY.MM.DD`, though some locales or builds may use `-` or `/` instead.
+// the same one throughout, which `find_header` checks
+const DEFAULT_HEADER_PATTERN: &str = "(?m)^(?-u:\\xEF\\xBB\\xBF)?(?P<yyyy>\\d{4})(?P<sep1>[./-])(?P<MM>\\d{2})(?P<sep2>[./-])(?P<dd>\\d{2}) (?P<hh>\\d{2}):(?P<mm>\\d{2}):(?P<ss>\\d{2}) ";
+const HEADER_GROUPS: [&str; 6] = ["yyyy", "MM", "dd", "hh", "mm", "ss"];
+
+/// The first match of `header_pattern` in `head` whose date separators, if captured, are
+/// the same, which the regex itself cannot tell for lack of backreferences.
+pub fn find_header<'h>(header_pattern: &Regex, head: &'h [u8]) -> Option<Captures<'h>> {
+    header_pattern.captures_iter(head).find(|captures| {
+        match (captures.name("sep1"), captures.name("sep2")) {
+            (Some(sep1), Some(sep2)) => sep1.as_bytes() == sep2.as_bytes(),
+            _ => true,
+        }
+    })
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            interval_secs: 60,
+            idle_interval_secs: 600,
+            collection_path: None,
+            vrchat_log_dir: None,
+            vrchat_log_dirs: vec![],
+            link_mode: LinkMode::default(),
+            watch: false,
+            locale: None,
+            notify_on_archive: false,
+            retention_days: None,
+            compress_after_days: None,
+            merge_gap_secs: None,
+            partition_date: PartitionDate::default(),
+            utc_offset_minutes: None,
+            dedupe: false,
+            archive_index: false,
+            content_filter: None,
+            scan_max_bytes: None,
+            exclude: vec![],
+            filename_pattern: None,
+            header_pattern: None,
+            notification_level: NotificationLevel::default(),
+            max_notifications_per_minute: 5,
+            notification_cooldown_secs: 3600,
+            quiet_hours: None,
+            quiet_hours_hold: true,
+            quiet_hours_crashes: true,
+            confirm_exit: true,
+            exit_summary: true,
+            pin_to_quick_access: false,
+            check_for_updates: false,
+            partition_template: partition::DEFAULT_TEMPLATE.to_string(),
+            mmap_headers: false,
+            archive_existing: true,
+            min_age_secs: 0,
+            keep_recent: 0,
+            min_free_space_mb: 512,
+            ntfs_compression: false,
+            timestamp_names: false,
+            world_folders: false,
+            flat_mirror: false,
+            crash_dumps: false,
+            crash_dump_dir: None,
+        }
+    }
+}
+
+impl Config {
+    pub fn load(config_path: &Path) -> Result<Self> {
+        let text = match fs::read_to_string(config_path) {
+            Ok(text) => text,
+            Err(e) => match e.kind() {
+                io::ErrorKind::NotFound => return Ok(Self::default()),
+                _ => return Err(e.into()),
+            },
+        };
+        let mut config: Self = toml::from_str(&text)
+            .with_context(|| format!("failed to parse {}", config_path.display()))?;
+        // the working directory is arbitrary (e.g. System32 when started at login),
+        // so relative paths are taken relative to the config file instead
+        let base_path = config_path.parent().unwrap();
+        if let Some(ref mut collection_path) = config.collection_path {
+            *collection_path = base_path.join(&collection_path);
+        }
+        if let Some(ref mut vrchat_log_dir) = config.vrchat_log_dir {
+            *vrchat_log_dir = base_path.join(&vrchat_log_dir);
+        }
+        for vrchat_log_dir in &mut config.vrchat_log_dirs {
+            *vrchat_log_dir = base_path.join(&vrchat_log_dir);
+        }
+        if let Some(ref mut crash_dump_dir) = config.crash_dump_dir {
+            *crash_dump_dir = base_path.join(&crash_dump_dir);
+        }
+        config
+            .validate()
+            .with_context(|| format!("invalid settings in {}", config_path.display()))?;
+        Ok(config)
+    }
+
+    pub fn with_locallow_path(locallow_path: &Path) -> Result<Self> {
+        Self::load(&Self::path(locallow_path))
+    }
+
+    pub fn path(locallow_path: &Path) -> PathBuf {
+        crate::app_data_path(locallow_path).join("config.toml")
+    }
+
+    pub fn save(&self, config_path: &Path) -> Result<()> {
+        let text = toml::to_string(self)?;
+        fs::create_dir_all(config_path.parent().unwrap())?;
+        fs::write(config_path, text)
+            .with_context(|| format!("failed to write {}", config_path.display()))
+    }
+
+    pub fn validate(&self) -> Result<()> {
+        if self.interval_secs < 1 {
+            anyhow::bail!("interval_secs must be at least 1 second");
+        }
+        if self.max_notifications_per_minute < 1 {
+            anyhow::bail!("max_notifications_per_minute must be at least 1");
+        }
+        if self.retention_days == Some(0) {
+            anyhow::bail!("retention_days must be at least 1 day");
+        }
+        if self.compress_after_days == Some(0) {
+            anyhow::bail!("compress_after_days must be at least 1 day");
+        }
+        if let Some(ref quiet_hours) = self.quiet_hours {
+            QuietHours::parse(quiet_hours)?;
+        }
+        if self.utc_offset_minutes.is_some() && self.utc_offset().is_none() {
+            anyhow::bail!("utc_offset_minutes must be within a day");
+        }
+        PartitionTemplate::new(&self.partition_template)?;
+        if let Some(ref content_filter) = self.content_filter {
+            Regex::new(content_filter).context("content_filter is not a valid regex")?;
+        }
+        if let Err(e) = RegexSet::new(&self.exclude) {
+            return Err(anyhow::Error::new(e).context("exclude holds an invalid regex"));
+        }
+        if let Some(ref filename_pattern) = self.filename_pattern {
+            regex::Regex::new(filename_pattern).context("filename_pattern is not a valid regex")?;
+        }
+        if let Some(ref header_pattern) = self.header_pattern {
+            let header_pattern =
+                Regex::new(header_pattern).context("header_pattern is not a valid regex")?;
+            for name in HEADER_GROUPS {
+                if !header_pattern.capture_names().flatten().any(|n| n == name) {
+                    anyhow::bail!("header_pattern must capture a group named {}", name);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    pub fn content_filter(&self) -> Option<Regex> {
+        self.content_filter
+            .as_ref()
+            .and_then(|content_filter| Regex::new(content_filter).ok())
+    }
+
+    pub fn exclude(&self) -> RegexSet {
+        RegexSet::new(&self.exclude).unwrap_or_else(|_| RegexSet::empty())
+    }
+
+    pub fn filename_pattern(&self) -> regex::Regex {
+        self.filename_pattern
+            .as_ref()
+            .and_then(|filename_pattern| regex::Regex::new(filename_pattern).ok())
+            .unwrap_or_else(|| regex::Regex::new(DEFAULT_FILENAME_PATTERN).unwrap())
+    }
+
+    pub fn header_pattern(&self) -> Regex {
+        self.header_pattern
+            .as_ref()
+            .and_then(|header_pattern| Regex::new(header_pattern).ok())
+            .unwrap_or_else(|| Regex::new(DEFAULT_HEADER_PATTERN).unwrap())
+    }
+
+    pub fn partition_template(&self) -> PartitionTemplate {
+        PartitionTemplate::new(&self.partition_template).unwrap_or_default()
+    }
+
+    pub fn utc_offset(&self) -> Option<FixedOffset> {
+        self.utc_offset_minutes
+            .and_then(|minutes| minutes.checked_mul(60))
+            .and_then(FixedOffset::east_opt)
+    }
+
+    pub fn interval(&self) -> Duration {
+        Duration::from_secs(self.interval_secs)
+    }
+
+    pub fn idle_interval(&self) -> Duration {
+        Duration::from_secs(self.idle_interval_secs)
+    }
+
+    pub fn min_free_space(&self) -> u64 {
+        self.min_free_space_mb.saturating_mul(1024 * 1024)
+    }
+
+    pub fn min_age(&self) -> Duration {
+        Duration::from_secs(self.min_age_secs)
+    }
+
+    /// The folder to archive crash dumps from, if they are to be archived at all.
+    pub fn crash_dump_dir(&self) -> Option<PathBuf> {
+        if !self.crash_dumps {
+            return None;
+        }
+        Some(self.crash_dump_dir.clone().unwrap_or_else(|| {
+            // where Unity's crash handler puts a folder for each crash
+            std::env::temp_dir().join("VRChat\\VRChat\\Crashes")
+        }))
+    }
+
+    pub fn merge_gap(&self) -> Option<chrono::Duration> {
+        self.merge_gap_secs
+            .and_then(|secs| chrono::Duration::from_std(Duration::from_secs(secs)).ok())
+    }
+
+    pub fn notification_cooldown(&self) -> Duration {
+        Duration::from_secs(self.notification_cooldown_secs)
+    }
+
+    pub fn quiet_hours(&self) -> Option<QuietHours> {
+        self.quiet_hours
+            .as_ref()
+            .and_then(|quiet_hours| QuietHours::parse(quiet_hours).ok())
+    }
+}
+
+/// A time of day range, which may span midnight as in `22:00-07:00`.
+#[derive(Debug, Clone, Copy)]
+pub struct QuietHours {
+    start: NaiveTime,
+    end: NaiveTime,
+}
+
+impl QuietHours {
+    pub fn parse(range: &str) -> Result<Self> {
+        let (start, end) = range
+            .split_once('-')
+            .ok_or_else(|| anyhow::anyhow!("quiet_hours must look like 22:00-07:00"))?;
+        let parse = |time: &str| {
+            NaiveTime::parse_from_str(time.trim(), "%H:%M")
+                .with_context(|| format!("quiet_hours holds an invalid time: {}", time))
+        };
+        Ok(Self {
+            start: parse(start)?,
+            end: parse(end)?,
+        })
+    }
+
+    /// Including `start` but not `end`, so that `07:00-07:00` is never quiet.
+    pub fn contains(&self, time: NaiveTime) -> bool {
+        if self.start <= self.end {
+            self.start <= time && time < self.end
+        } else {
+            self.start <= time || time < self.end
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn header_date(head: &[u8]) -> Option<(String, String, String)> {
+        let captures = find_header(&Config::default().header_pattern(), head)?;
+        let group = |name: &str| String::from_utf8(captures[name].to_vec()).unwrap();
+        Some((group("yyyy"), group("MM"), group("dd")))
+    }
+
+    #[test]
+    fn header_pattern_accepts_each_separator() {
+        for head in [
+            &b"2024.01.31 12:34:56 Log        -  [Behaviour] ...\n"[..],
+            b"2024-01-31 12:34:56 Log        -  [Behaviour] ...\n",
+            b"2024/01/31 12:34:56 Log        -  [Behaviour] ...\n",
+        ] {
+            let expected = ("2024".to_string(), "01".to_string(), "31".to_string());
+            assert_eq!(header_date(head), Some(expected), "{:?}", head);
+        }
+    }
+
+    #[test]
+    fn header_pattern_rejects_mixed_or_invalid_separators() {
+        for head in [
+            &b"2024.01-31 12:34:56 Log\n"[..],
+            b"2024/01.31 12:34:56 Log\n",
+            b"2024_01_31 12:34:56 Log\n",
+            b"2024:01:31 12:34:56 Log\n",
+            b"20240131 12:34:56 Log\n",
+        ] {
+            assert_eq!(header_date(head), None, "{:?}", head);
+        }
+    }
+
+    #[test]
+    fn header_pattern_skips_a_mixed_line_for_a_later_one() {
+        let head = b"2024.01-31 12:34:56 Log\n2024.02.01 00:00:00 Log\n";
+        let expected = ("2024".to_string(), "02".to_string(), "01".to_string());
+        assert_eq!(header_date(head), Some(expected));
+    }
+
+    #[test]
+    fn header_pattern_allows_a_bom() {
+        let head = b"\xEF\xBB\xBF2024.01.31 12:34:56 Log\n";
+        let expected = ("2024".to_string(), "01".to_string(), "31".to_string());
+        assert_eq!(header_date(head), Some(expected));
+        let captures = find_header(&Config::default().header_pattern(), head).unwrap();
+        assert_eq!(&captures["hh"], b"12");
+        assert_eq!(&captures["ss"], b"56");
+    }
+
+    #[test]
+    fn notification_level_defaults_to_errors_only() {
+        assert_eq!(
+            Config::default().notification_level,
+            NotificationLevel::ErrorsOnly
+        );
+        let config: Config = toml::from_str("").unwrap();
+        assert_eq!(config.notification_level, NotificationLevel::ErrorsOnly);
+        let config: Config = toml::from_str("notification_level = \"verbose\"").unwrap();
+        assert_eq!(config.notification_level, NotificationLevel::Verbose);
+    }
+
+    #[test]
+    fn filename_pattern_matches_both_namings() {
+        let re = Config::default().filename_pattern();
+        assert!(re.is_match("output_log_12-34-56.txt"));
+        assert!(re.is_match("output_log_2024-01-31_12-34-56.txt"));
+    }
+
+    #[test]
+    fn filename_pattern_rejects_near_misses() {
+        let re = Config::default().filename_pattern();
+        for file_name in [
+            "output_log_12-34-5.txt",
+            "output_log_12-34-56.log",
+            "output_log_12-34-56.txt.gz",
+            "output_log_2024-01-31-12-34-56.txt",
+            "output_log_2024-01-31_12-34-56_2.txt",
+            "output_log_24-01-31_12-34-56.txt",
+            "Output_log_12-34-56.txt",
+            "old_output_log_12-34-56.txt",
+            "output_log_.txt",
+        ] {
+            assert!(!re.is_match(file_name), "{}", file_name);
+        }
+    }
+}
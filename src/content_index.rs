@@ -0,0 +1,105 @@
+use std::collections::HashMap;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+use sha2::{Digest, Sha256};
+
+/// Remembers the content hash of every archived logfile, so that identical content
+/// arriving under another name or date is not archived twice.
+///
+/// Stored as `<hash>\t<path relative to the collection>` lines in the collection root.
+#[derive(Clone)]
+pub struct ContentIndex {
+    collection_path: PathBuf,
+    // loaded on first use
+    entries: Option<HashMap<String, PathBuf>>,
+}
+
+impl ContentIndex {
+    pub fn new(collection_path: PathBuf) -> Self {
+        Self {
+            collection_path,
+            entries: None,
+        }
+    }
+
+    fn index_path(&self) -> PathBuf {
+        self.collection_path.join("content_index.txt")
+    }
+
+    fn entries(&mut self) -> io::Result<&mut HashMap<String, PathBuf>> {
+        if self.entries.is_none() {
+            let text = match fs::read_to_string(self.index_path()) {
+                Ok(text) => text,
+                Err(e) => match e.kind() {
+                    io::ErrorKind::NotFound => String::new(),
+                    _ => return Err(e),
+                },
+            };
+            let entries = text
+                .lines()
+                .filter_map(|line| {
+                    let (hash, path) = line.split_once('\t')?;
+                    Some((hash.to_owned(), PathBuf::from(path)))
+                })
+                .collect();
+            self.entries = Some(entries);
+        }
+        Ok(self.entries.as_mut().unwrap())
+    }
+
+    /// Returns the archived file with the given content hash, if it still exists.
+    pub fn find(&mut self, hash: &str) -> io::Result<Option<PathBuf>> {
+        let collection_path = self.collection_path.clone();
+        Ok(self
+            .entries()?
+            .get(hash)
+            .map(|path| collection_path.join(path))
+            .filter(|path| path.exists()))
+    }
+
+    pub fn insert(&mut self, hash: String, path: &Path) -> io::Result<()> {
+        let relative_path = path
+            .strip_prefix(&self.collection_path)
+            .unwrap_or(path)
+            .to_owned();
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(self.index_path())?;
+        writeln!(file, "{}\t{}", hash, relative_path.display())?;
+        self.entries()?.insert(hash, relative_path);
+        Ok(())
+    }
+
+    /// Points the entries for `from` at `to` instead, after a file has been moved within
+    /// the collection.
+    pub fn rename(&mut self, from: &Path, to: &Path) -> io::Result<()> {
+        let from = from
+            .strip_prefix(&self.collection_path)
+            .unwrap_or(from)
+            .to_owned();
+        let hashes: Vec<_> = self
+            .entries()?
+            .iter()
+            .filter(|&(_, path)| *path == from)
+            .map(|(hash, _)| hash.clone())
+            .collect();
+        // appended like any other entry, as later lines take precedence
+        for hash in hashes {
+            self.insert(hash, to)?;
+        }
+        Ok(())
+    }
+}
+
+pub fn hash_file(path: &Path) -> io::Result<String> {
+    let mut hasher = Sha256::new();
+    io::copy(&mut File::open(path)?, &mut hasher)?;
+    Ok(hasher
+        .finalize()
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect())
+}
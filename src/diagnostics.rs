@@ -0,0 +1,77 @@
+use std::fmt;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+
+use chrono::Local;
+
+const MAX_LEN: u64 = 1024 * 1024;
+
+/// An append-only log for troubleshooting, rotated once it grows past `MAX_LEN`.
+#[derive(Clone, Default)]
+pub struct Diagnostics {
+    path: PathBuf,
+}
+
+impl Diagnostics {
+    pub fn new(path: PathBuf) -> Self {
+        Self { path }
+    }
+
+    pub fn with_locallow_path(locallow_path: &Path) -> Self {
+        let path = crate::app_data_path(locallow_path).join("diagnostics.log");
+        Self::new(path)
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// The last lines of the log, at most `max_len` bytes of them.
+    pub fn tail(&self, max_len: u64) -> io::Result<String> {
+        let mut file = match File::open(&self.path) {
+            Ok(file) => file,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(String::new()),
+            Err(e) => return Err(e),
+        };
+        let len = file.metadata()?.len();
+        file.seek(SeekFrom::Start(len.saturating_sub(max_len)))?;
+        let mut buf = vec![];
+        file.read_to_end(&mut buf)?;
+        // starting mid-line, unless the whole log fit
+        let start = if len > max_len {
+            buf.iter()
+                .position(|&b| b == b'\n')
+                .map_or(buf.len(), |i| i + 1)
+        } else {
+            0
+        };
+        Ok(String::from_utf8_lossy(&buf[start..]).into_owned())
+    }
+
+    /// Failing to write diagnostics must never disturb the caller, so errors are dropped.
+    pub fn log(&self, args: fmt::Arguments) {
+        let _ = self.try_log(args);
+    }
+
+    fn try_log(&self, args: fmt::Arguments) -> io::Result<()> {
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        if fs::metadata(&self.path).is_ok_and(|metadata| metadata.len() > MAX_LEN) {
+            let mut rotated_path = self.path.clone().into_os_string();
+            rotated_path.push(".1");
+            fs::rename(&self.path, rotated_path)?;
+        }
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?;
+        writeln!(
+            file,
+            "{} {}",
+            Local::now().format("%Y-%m-%d %H:%M:%S"),
+            args
+        )
+    }
+}
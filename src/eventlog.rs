@@ -0,0 +1,43 @@
+use std::ffi::OsStr;
+use std::iter;
+use std::os::windows::ffi::OsStrExt;
+use std::ptr;
+
+use winapi::um::{
+    winbase::{DeregisterEventSource, RegisterEventSourceW, ReportEventW},
+    winnt::{EVENTLOG_ERROR_TYPE, LPCWSTR},
+};
+
+use crate::APP_NAME;
+
+/// Writes an error entry to the Application event log.
+/// This is best-effort: failures are silently ignored.
+pub fn report_error(message: &str) {
+    let source: Vec<_> = OsStr::new(APP_NAME)
+        .encode_wide()
+        .chain(iter::once(0))
+        .collect();
+    let message: Vec<_> = OsStr::new(message)
+        .encode_wide()
+        .chain(iter::once(0))
+        .collect();
+    unsafe {
+        let handle = RegisterEventSourceW(ptr::null(), source.as_ptr());
+        if handle.is_null() {
+            return;
+        }
+        let mut strings: [LPCWSTR; 1] = [message.as_ptr()];
+        ReportEventW(
+            handle,
+            EVENTLOG_ERROR_TYPE,
+            0,
+            0,
+            ptr::null_mut(),
+            strings.len() as u16,
+            0,
+            strings.as_mut_ptr(),
+            ptr::null_mut(),
+        );
+        DeregisterEventSource(handle);
+    }
+}
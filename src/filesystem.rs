@@ -0,0 +1,454 @@
+use std::fs;
+use std::io::{self, Read};
+use std::ops::Deref;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use crate::mmap::MappedPrefix;
+
+/// What `FileSystem::metadata` tells about a file or folder.
+#[derive(Debug, Clone, Copy)]
+pub struct Metadata {
+    pub len: u64,
+    pub modified: SystemTime,
+    /// Not kept by every drive.
+    pub created: Option<SystemTime>,
+}
+
+/// The beginning of a file, see `FileSystem::read_head`.
+pub enum Head {
+    Mapped(MappedPrefix),
+    Read(Vec<u8>),
+}
+
+impl Deref for Head {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        match self {
+            Head::Mapped(mapped) => mapped,
+            Head::Read(read) => read,
+        }
+    }
+}
+
+/// The filesystem calls that archiving makes: listing the VRChat folders, reading the logs
+/// and placing them in the collection along with their sidecars, manifests and the state,
+/// so that tests can run it all on an in-memory tree. The content index and maintenance of
+/// the collection, e.g. compression, go straight to the drive.
+pub trait FileSystem {
+    /// The entries of `folder`, each along with whether it is a folder itself.
+    fn read_dir(&self, folder: &Path) -> io::Result<Vec<(PathBuf, bool)>>;
+
+    fn metadata(&self, path: &Path) -> io::Result<Metadata>;
+
+    /// Opens the file at `path` for reading, even while VRChat is writing to it.
+    fn open(&self, path: &Path) -> io::Result<Box<dyn Read>>;
+
+    /// Up to `max_len` bytes from the start of the file at `path`, with `mmap` in a
+    /// memory-mapped view where the file can be mapped.
+    fn read_head(&self, path: &Path, max_len: usize, mmap: bool) -> io::Result<Head>;
+
+    fn read(&self, path: &Path) -> io::Result<Vec<u8>>;
+
+    fn write(&self, path: &Path, contents: &[u8]) -> io::Result<()>;
+
+    fn rename(&self, from: &Path, to: &Path) -> io::Result<()>;
+
+    fn remove_file(&self, path: &Path) -> io::Result<()>;
+
+    fn create_dir_all(&self, path: &Path) -> io::Result<()>;
+
+    fn create_compressed_dir_all(&self, path: &Path) -> io::Result<()>;
+
+    fn hard_link(&self, original: &Path, link: &Path) -> io::Result<()>;
+
+    fn symlink_file(&self, original: &Path, link: &Path) -> io::Result<()>;
+
+    /// Copies `from` to `to` along with its timestamps, see `crate::copy_verified`.
+    fn copy(&self, from: &Path, to: &Path) -> io::Result<()>;
+
+    /// The space available on the drive of `path`, see `crate::free_space`.
+    fn free_space(&self, path: &Path) -> io::Result<u64>;
+
+    /// Whether VRChat still has the log at `path` open, see `crate::is_in_use`.
+    fn is_in_use(&self, path: &Path) -> io::Result<bool>;
+
+    /// The regular files in `folder`.
+    fn list_files(&self, folder: &Path) -> io::Result<Vec<PathBuf>> {
+        Ok(self
+            .read_dir(folder)?
+            .into_iter()
+            .filter(|&(_, is_dir)| !is_dir)
+            .map(|(path, _)| path)
+            .collect())
+    }
+
+    fn exists(&self, path: &Path) -> bool {
+        self.metadata(path).is_ok()
+    }
+}
+
+pub struct OsFileSystem;
+
+impl FileSystem for OsFileSystem {
+    fn read_dir(&self, folder: &Path) -> io::Result<Vec<(PathBuf, bool)>> {
+        let mut entries = vec![];
+        for entry in folder.read_dir()? {
+            let entry = entry?;
+            let file_type = entry.file_type()?;
+            // anything else, such as a symlink, is left out as `partition_files` always did
+            if file_type.is_dir() || file_type.is_file() {
+                entries.push((entry.path(), file_type.is_dir()));
+            }
+        }
+        Ok(entries)
+    }
+
+    fn metadata(&self, path: &Path) -> io::Result<Metadata> {
+        let metadata = fs::metadata(path)?;
+        Ok(Metadata {
+            len: metadata.len(),
+            modified: metadata.modified()?,
+            created: metadata.created().ok(),
+        })
+    }
+
+    fn open(&self, path: &Path) -> io::Result<Box<dyn Read>> {
+        Ok(Box::new(crate::VRCLogfile::open(path)?))
+    }
+
+    fn read_head(&self, path: &Path, max_len: usize, mmap: bool) -> io::Result<Head> {
+        let file = crate::VRCLogfile::open(path)?;
+        // e.g. a file that is still empty cannot be mapped
+        if let Some(Ok(mapped)) = mmap.then(|| MappedPrefix::new(&file, max_len)) {
+            return Ok(Head::Mapped(mapped));
+        }
+        let mut head = vec![];
+        file.take(max_len as u64).read_to_end(&mut head)?;
+        Ok(Head::Read(head))
+    }
+
+    fn read(&self, path: &Path) -> io::Result<Vec<u8>> {
+        fs::read(path)
+    }
+
+    fn write(&self, path: &Path, contents: &[u8]) -> io::Result<()> {
+        fs::write(path, contents)
+    }
+
+    fn rename(&self, from: &Path, to: &Path) -> io::Result<()> {
+        fs::rename(from, to)
+    }
+
+    fn remove_file(&self, path: &Path) -> io::Result<()> {
+        fs::remove_file(path)
+    }
+
+    fn create_dir_all(&self, path: &Path) -> io::Result<()> {
+        fs::create_dir_all(path)
+    }
+
+    fn create_compressed_dir_all(&self, path: &Path) -> io::Result<()> {
+        crate::create_compressed_dir_all(path)
+    }
+
+    fn hard_link(&self, original: &Path, link: &Path) -> io::Result<()> {
+        fs::hard_link(original, link)
+    }
+
+    fn symlink_file(&self, original: &Path, link: &Path) -> io::Result<()> {
+        std::os::windows::fs::symlink_file(original, link)
+    }
+
+    fn copy(&self, from: &Path, to: &Path) -> io::Result<()> {
+        crate::copy_verified(from, to)
+    }
+
+    fn free_space(&self, path: &Path) -> io::Result<u64> {
+        crate::free_space(path)
+    }
+
+    fn is_in_use(&self, path: &Path) -> io::Result<bool> {
+        crate::is_in_use(path)
+    }
+}
+
+#[cfg(test)]
+pub use fake::FakeFileSystem;
+
+#[cfg(test)]
+mod fake {
+    use std::collections::{BTreeMap, HashSet};
+    use std::io::{self, Read};
+    use std::path::{Path, PathBuf};
+    use std::sync::atomic::{AtomicU64, Ordering};
+    use std::sync::{Arc, Mutex};
+    use std::time::SystemTime;
+
+    use super::{FileSystem, Head, Metadata};
+
+    #[derive(Debug)]
+    struct File {
+        contents: Vec<u8>,
+        modified: SystemTime,
+        created: SystemTime,
+    }
+
+    #[derive(Debug, Clone)]
+    enum Entry {
+        Folder,
+        /// Shared by all hard links to the file. A symlink shares it as well, as nothing
+        /// archived ever tells the two apart.
+        File(Arc<Mutex<File>>),
+    }
+
+    fn not_found(path: &Path) -> io::Error {
+        io::Error::new(
+            io::ErrorKind::NotFound,
+            format!("{} not found", path.display()),
+        )
+    }
+
+    /// A drive in memory, along with faults set up by the test that a real drive seldom
+    /// shows. Starts out empty, apart from the drive root that every path is below.
+    #[derive(Debug, Default)]
+    pub struct FakeFileSystem {
+        entries: Mutex<BTreeMap<PathBuf, Entry>>,
+        /// Listed along with the files in their folder, as if removed right after.
+        pub vanished: Vec<PathBuf>,
+        /// Reported as the free space of every drive.
+        pub free_space: AtomicU64,
+        /// Files reported as open by VRChat.
+        pub in_use: Mutex<HashSet<PathBuf>>,
+        /// Fails to create compressed folders as on a drive that does not support it.
+        pub no_compression: bool,
+    }
+
+    impl FakeFileSystem {
+        /// Without any faults, and with plenty of free space.
+        pub fn new() -> Self {
+            Self {
+                free_space: u64::MAX.into(),
+                ..Self::default()
+            }
+        }
+
+        fn file(&self, path: &Path) -> io::Result<Arc<Mutex<File>>> {
+            match self.entries.lock().unwrap().get(path) {
+                Some(Entry::File(file)) => Ok(file.clone()),
+                Some(Entry::Folder) => Err(io::Error::new(
+                    io::ErrorKind::PermissionDenied,
+                    format!("{} is a folder", path.display()),
+                )),
+                None => Err(not_found(path)),
+            }
+        }
+
+        /// Fails unless the parent of `path` is a folder, as creating a file there would.
+        fn check_parent(entries: &BTreeMap<PathBuf, Entry>, path: &Path) -> io::Result<()> {
+            match path.parent() {
+                Some(parent) if matches!(entries.get(parent), Some(Entry::Folder)) => Ok(()),
+                // the drive root
+                Some(parent) if parent.parent().is_none() => Ok(()),
+                _ => Err(not_found(path)),
+            }
+        }
+
+        /// Adds `file` at `link`, which has to be free.
+        fn insert_new(&self, link: &Path, file: Arc<Mutex<File>>) -> io::Result<()> {
+            let mut entries = self.entries.lock().unwrap();
+            Self::check_parent(&entries, link)?;
+            if entries.contains_key(link) {
+                return Err(io::Error::new(
+                    io::ErrorKind::AlreadyExists,
+                    format!("{} already exists", link.display()),
+                ));
+            }
+            entries.insert(link.to_owned(), Entry::File(file));
+            Ok(())
+        }
+
+        /// Replaces what the file at `path` holds, in place like a real write to a hard
+        /// link, or creates it. Its creation time only changes if `created` is given.
+        fn put(
+            &self,
+            path: &Path,
+            contents: Vec<u8>,
+            modified: SystemTime,
+            created: Option<SystemTime>,
+        ) -> io::Result<()> {
+            let mut entries = self.entries.lock().unwrap();
+            Self::check_parent(&entries, path)?;
+            match entries.get(path) {
+                Some(Entry::File(file)) => {
+                    let mut file = file.lock().unwrap();
+                    file.contents = contents;
+                    file.modified = modified;
+                    file.created = created.unwrap_or(file.created);
+                }
+                Some(Entry::Folder) => {
+                    return Err(io::Error::new(
+                        io::ErrorKind::PermissionDenied,
+                        format!("{} is a folder", path.display()),
+                    ))
+                }
+                None => {
+                    let file = File {
+                        contents,
+                        modified,
+                        created: created.unwrap_or(modified),
+                    };
+                    entries.insert(path.to_owned(), Entry::File(Arc::new(Mutex::new(file))));
+                }
+            }
+            Ok(())
+        }
+    }
+
+    impl FileSystem for FakeFileSystem {
+        fn read_dir(&self, folder: &Path) -> io::Result<Vec<(PathBuf, bool)>> {
+            let entries = self.entries.lock().unwrap();
+            if !matches!(entries.get(folder), Some(Entry::Folder)) {
+                return Err(not_found(folder));
+            }
+            let mut listed: Vec<_> = entries
+                .iter()
+                .filter(|(path, _)| path.parent() == Some(folder))
+                .map(|(path, entry)| (path.clone(), matches!(entry, Entry::Folder)))
+                .collect();
+            listed.extend(
+                self.vanished
+                    .iter()
+                    .filter(|path| path.parent() == Some(folder))
+                    .map(|path| (path.clone(), false)),
+            );
+            Ok(listed)
+        }
+
+        fn metadata(&self, path: &Path) -> io::Result<Metadata> {
+            let entry = self.entries.lock().unwrap().get(path).cloned();
+            match entry {
+                Some(Entry::File(file)) => {
+                    let file = file.lock().unwrap();
+                    Ok(Metadata {
+                        len: file.contents.len() as u64,
+                        modified: file.modified,
+                        created: Some(file.created),
+                    })
+                }
+                Some(Entry::Folder) => Ok(Metadata {
+                    len: 0,
+                    modified: SystemTime::UNIX_EPOCH,
+                    created: Some(SystemTime::UNIX_EPOCH),
+                }),
+                None => Err(not_found(path)),
+            }
+        }
+
+        fn open(&self, path: &Path) -> io::Result<Box<dyn Read>> {
+            Ok(Box::new(io::Cursor::new(self.read(path)?)))
+        }
+
+        fn read_head(&self, path: &Path, max_len: usize, _mmap: bool) -> io::Result<Head> {
+            let mut head = self.read(path)?;
+            head.truncate(max_len);
+            Ok(Head::Read(head))
+        }
+
+        fn read(&self, path: &Path) -> io::Result<Vec<u8>> {
+            Ok(self.file(path)?.lock().unwrap().contents.clone())
+        }
+
+        fn write(&self, path: &Path, contents: &[u8]) -> io::Result<()> {
+            self.put(path, contents.to_vec(), SystemTime::now(), None)
+        }
+
+        fn rename(&self, from: &Path, to: &Path) -> io::Result<()> {
+            let mut entries = self.entries.lock().unwrap();
+            Self::check_parent(&entries, to)?;
+            let moved: Vec<_> = entries
+                .keys()
+                .filter(|path| path.starts_with(from))
+                .cloned()
+                .collect();
+            if moved.is_empty() {
+                return Err(not_found(from));
+            }
+            if matches!(entries.get(to), Some(Entry::Folder)) {
+                return Err(io::Error::new(
+                    io::ErrorKind::PermissionDenied,
+                    format!("{} is a folder", to.display()),
+                ));
+            }
+            for path in moved {
+                let entry = entries.remove(&path).unwrap();
+                entries.insert(to.join(path.strip_prefix(from).unwrap()), entry);
+            }
+            Ok(())
+        }
+
+        fn remove_file(&self, path: &Path) -> io::Result<()> {
+            self.file(path)?;
+            self.entries.lock().unwrap().remove(path);
+            Ok(())
+        }
+
+        fn create_dir_all(&self, path: &Path) -> io::Result<()> {
+            let mut entries = self.entries.lock().unwrap();
+            // the drive root is always there
+            for folder in path.ancestors().filter(|folder| folder.parent().is_some()) {
+                match entries.get(folder) {
+                    Some(Entry::Folder) => {}
+                    Some(Entry::File(_)) => {
+                        return Err(io::Error::new(
+                            io::ErrorKind::AlreadyExists,
+                            format!("{} is a file", folder.display()),
+                        ))
+                    }
+                    None => {
+                        entries.insert(folder.to_owned(), Entry::Folder);
+                    }
+                }
+            }
+            Ok(())
+        }
+
+        fn create_compressed_dir_all(&self, path: &Path) -> io::Result<()> {
+            if self.no_compression {
+                return Err(io::Error::new(
+                    io::ErrorKind::Unsupported,
+                    "compression is not supported",
+                ));
+            }
+            self.create_dir_all(path)
+        }
+
+        fn hard_link(&self, original: &Path, link: &Path) -> io::Result<()> {
+            let file = self.file(original)?;
+            self.insert_new(link, file)
+        }
+
+        fn symlink_file(&self, original: &Path, link: &Path) -> io::Result<()> {
+            self.hard_link(original, link)
+        }
+
+        fn copy(&self, from: &Path, to: &Path) -> io::Result<()> {
+            let (contents, modified, created) = {
+                let file = self.file(from)?;
+                let file = file.lock().unwrap();
+                (file.contents.clone(), file.modified, file.created)
+            };
+            self.put(to, contents, modified, Some(created))
+        }
+
+        fn free_space(&self, _path: &Path) -> io::Result<u64> {
+            Ok(self.free_space.load(Ordering::SeqCst))
+        }
+
+        fn is_in_use(&self, path: &Path) -> io::Result<bool> {
+            self.file(path)?;
+            Ok(self.in_use.lock().unwrap().contains(path))
+        }
+    }
+}
@@ -0,0 +1,83 @@
+use std::ffi::OsStr;
+use std::io;
+use std::iter;
+use std::os::windows::ffi::OsStrExt;
+use std::path::Path;
+use std::ptr;
+
+use winapi::shared::minwindef::FALSE;
+use winapi::shared::winerror::ERROR_ALREADY_EXISTS;
+use winapi::um::{
+    handleapi::CloseHandle,
+    synchapi::{CreateEventW, CreateMutexW, SetEvent, WaitForSingleObject},
+    winbase::{INFINITE, WAIT_OBJECT_0},
+    winnt::HANDLE,
+    winuser::{AllowSetForegroundWindow, ASFW_ANY},
+};
+
+use crate::APP_NAME;
+
+fn to_wide(s: &str) -> Vec<u16> {
+    OsStr::new(s).encode_wide().chain(iter::once(0)).collect()
+}
+
+/// Held by the one instance running the worker for a data folder, so that instances
+/// started in portable mode elsewhere are not affected.
+pub struct Instance {
+    mutex: HANDLE,
+    // auto-reset, set by a later instance to have this one show its menu
+    show_event: HANDLE,
+}
+
+// both handles may be waited on and closed from any thread
+unsafe impl Send for Instance {}
+unsafe impl Sync for Instance {}
+
+impl Instance {
+    /// Returns `None` if another instance already holds `data_path`, after asking it
+    /// to show its menu.
+    pub fn acquire(data_path: &Path) -> io::Result<Option<Self>> {
+        // kernel object names may not contain backslashes, and paths differ only in case
+        let key = data_path.to_string_lossy().to_lowercase();
+        let key = format!("{:08x}", crc32fast::hash(key.as_bytes()));
+        let mutex_name = to_wide(&format!("Local\\{}-{}", APP_NAME, key));
+        let event_name = to_wide(&format!("Local\\{}-{}-show", APP_NAME, key));
+        unsafe {
+            let show_event = CreateEventW(ptr::null_mut(), FALSE, FALSE, event_name.as_ptr());
+            if show_event.is_null() {
+                return Err(io::Error::last_os_error());
+            }
+            let mutex = CreateMutexW(ptr::null_mut(), FALSE, mutex_name.as_ptr());
+            if mutex.is_null() {
+                let e = io::Error::last_os_error();
+                CloseHandle(show_event);
+                return Err(e);
+            }
+            if io::Error::last_os_error().raw_os_error() == Some(ERROR_ALREADY_EXISTS as i32) {
+                // the menu can only take the foreground with our leave, which we have
+                // for having just been started by the user
+                AllowSetForegroundWindow(ASFW_ANY);
+                SetEvent(show_event);
+                CloseHandle(mutex);
+                CloseHandle(show_event);
+                return Ok(None);
+            }
+            Ok(Some(Self { mutex, show_event }))
+        }
+    }
+
+    /// Blocks until another instance asks for the menu. Returns `false` if that can no
+    /// longer happen.
+    pub fn wait_for_show(&self) -> bool {
+        unsafe { WaitForSingleObject(self.show_event, INFINITE) == WAIT_OBJECT_0 }
+    }
+}
+
+impl Drop for Instance {
+    fn drop(&mut self) {
+        unsafe {
+            CloseHandle(self.show_event);
+            CloseHandle(self.mutex);
+        }
+    }
+}
@@ -0,0 +1,258 @@
+use serde::{Deserialize, Serialize};
+use winapi::um::{
+    winnls::GetUserDefaultUILanguage,
+    winnt::{LANG_JAPANESE, PRIMARYLANGID},
+};
+
+#[derive(Debug, Clone, Copy, Default, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Locale {
+    En,
+    #[default]
+    Ja,
+}
+
+pub struct Strings {
+    pub open_collection: &'static str,
+    pub open_today: &'static str,
+    pub recent: &'static str,
+    pub pause: &'static str,
+    pub resume: &'static str,
+    pub paused_tip: &'static str,
+    pub scan_now: &'static str,
+    pub status_tip: fn(&str, usize) -> String,
+    pub progress_tip: fn(usize, usize) -> String,
+    pub archived_message: fn(usize) -> String,
+    pub settings: &'static str,
+    pub export_archive: &'static str,
+    pub export_source_title: &'static str,
+    pub export_outside_collection: &'static str,
+    pub exported_message: fn(&str, usize) -> String,
+    pub disk_usage: &'static str,
+    pub disk_usage_message: fn(&str, &str) -> String,
+    pub dashboard: &'static str,
+    pub dashboard_loading: &'static str,
+    pub dashboard_empty: &'static str,
+    pub dashboard_summary: fn(usize, &str, &str) -> String,
+    /// Month, number of logs and total size.
+    pub dashboard_columns: [&'static str; 3],
+    pub start_with_windows: &'static str,
+    pub open_diagnostics: &'static str,
+    pub copy_diagnostics: &'static str,
+    pub diagnostics_copied: &'static str,
+    pub self_test: &'static str,
+    /// In the order the checks of `self_test` are run.
+    pub self_test_checks: [&'static str; 4],
+    pub self_test_passed: &'static str,
+    pub self_test_failed: &'static str,
+    pub settings_interval: &'static str,
+    pub settings_collection_path: &'static str,
+    pub settings_link_mode: &'static str,
+    pub settings_retention_days: &'static str,
+    pub settings_restart_note: &'static str,
+    pub settings_save: &'static str,
+    pub settings_invalid: &'static str,
+    pub config_invalid_fallback: &'static str,
+    /// In the order of `LinkMode::ALL`.
+    pub link_modes: [&'static str; 3],
+    pub welcome_message: fn(&str) -> String,
+    pub open_releases: &'static str,
+    pub update_menu: fn(&str) -> String,
+    pub update_message: fn(&str) -> String,
+    pub exit: &'static str,
+    pub exit_confirm: &'static str,
+    pub session_summary: fn(usize) -> String,
+    pub purge_confirm: fn(&str) -> String,
+    pub locallow_missing: &'static str,
+    pub error_title: &'static str,
+    pub errors_message: fn(&str, usize) -> String,
+    pub crash_title: &'static str,
+    pub crash_message: &'static str,
+}
+
+static EN: Strings = Strings {
+    open_collection: "Open log folder",
+    open_today: "Open today's logs",
+    recent: "Recent days",
+    pause: "Pause",
+    resume: "Resume",
+    paused_tip: "VRCLogUnrotate (paused)",
+    scan_now: "Scan now",
+    status_tip: |scanned_at, archived_today| {
+        format!(
+            "Last scan: {}, {} log(s) archived today",
+            scanned_at, archived_today
+        )
+    },
+    progress_tip: |done, total| format!("Initial scan: {}/{} logs", done, total),
+    archived_message: |linked| format!("Archived {} new log(s)", linked),
+    settings: "Settings",
+    export_archive: "Export as zip...",
+    export_source_title: "Choose a month folder, or the log folder itself to export everything",
+    export_outside_collection: "The folder to export must be inside the log folder",
+    exported_message: |zip_path, files| format!("Exported {} file(s) to {}", files, zip_path),
+    disk_usage: "Show disk usage",
+    disk_usage_message: |logical, unique| {
+        format!(
+            "The log folder holds {} ({} counting hard-linked files once)",
+            logical, unique
+        )
+    },
+    dashboard: "Statistics",
+    dashboard_loading: "Counting archived logs...",
+    dashboard_empty: "No logs have been archived yet",
+    dashboard_summary: |logs, first, last| {
+        format!("{} log(s) archived from {} to {}", logs, first, last)
+    },
+    dashboard_columns: ["Month", "Logs", "Size"],
+    start_with_windows: "Start with Windows",
+    open_diagnostics: "Open diagnostics log",
+    copy_diagnostics: "Copy diagnostics",
+    diagnostics_copied: "Diagnostics copied to the clipboard, ready to paste into an issue.",
+    self_test: "Run self-test",
+    self_test_checks: [
+        "Find the LocalLow folder",
+        "Read the VRChat log folder",
+        "Create the collection folder",
+        "Hard link into the collection",
+    ],
+    self_test_passed: "Everything needed to archive logs works.",
+    self_test_failed: "Some checks failed, so logs may not be archived as expected.",
+    settings_interval: "Scan interval (seconds)",
+    settings_collection_path: "Collection folder (empty for default)",
+    settings_link_mode: "Archive method",
+    settings_retention_days: "Keep logs for (days, empty for forever)",
+    settings_restart_note: "Changing the collection folder takes effect after a restart.",
+    settings_save: "Save",
+    settings_invalid: "Invalid settings",
+    config_invalid_fallback: "Start with the default settings until the file is fixed?",
+    link_modes: ["Hard link", "Copy", "Symbolic link"],
+    welcome_message: |collection_path| {
+        format!(
+            "VRChat logs are now being collected to {}. Open them any time from the tray menu.",
+            collection_path
+        )
+    },
+    open_releases: "Open releases page",
+    update_menu: |tag| format!("Download {}", tag),
+    update_message: |tag| format!("{} is available. Open the tray menu to download it.", tag),
+    exit: "Exit",
+    exit_confirm: "Logs will not be archived until VRCLogUnrotate is started again. Exit anyway?",
+    session_summary: |archived| format!("Archived {} log(s) this session", archived),
+    purge_confirm: |collection_path| {
+        format!(
+            "Delete {} and every log archived in it? This cannot be undone.",
+            collection_path
+        )
+    },
+    locallow_missing: "Windows did not tell VRCLogUnrotate where the LocalLow folder is, so it cannot find VRChat's logs. Check that %USERPROFILE%\\AppData\\LocalLow exists and that your user profile loaded correctly, then start VRCLogUnrotate again.",
+    error_title: "An error occurred while VRCLogUnrotate was running",
+    errors_message: |first, count| format!("{} (and {} more error(s))", first, count - 1),
+    crash_title: "VRCLogUnrotate has crashed",
+    crash_message: "VRCLogUnrotate is exiting due to an unrecoverable error",
+};
+
+static JA: Strings = Strings {
+    open_collection: "ログのフォルダを開く",
+    open_today: "今日のログを開く",
+    recent: "最近のログ",
+    pause: "一時停止",
+    resume: "再開",
+    paused_tip: "VRCLogUnrotate (一時停止中)",
+    scan_now: "今すぐスキャン",
+    status_tip: |scanned_at, archived_today| {
+        format!(
+            "最終スキャン: {}、今日のアーカイブ: {}件",
+            scanned_at, archived_today
+        )
+    },
+    progress_tip: |done, total| format!("初回スキャン中: {}/{}件", done, total),
+    archived_message: |linked| format!("{}件の新しいログをアーカイブしました", linked),
+    settings: "設定",
+    export_archive: "zipでエクスポート...",
+    export_source_title: "月のフォルダを選択 (ログのフォルダを選ぶとすべてをエクスポート)",
+    export_outside_collection: "エクスポートするフォルダはログのフォルダ内から選んでください",
+    exported_message: |zip_path, files| {
+        format!("{}件のファイルを{}にエクスポートしました", files, zip_path)
+    },
+    disk_usage: "使用容量を表示",
+    disk_usage_message: |logical, unique| {
+        format!(
+            "ログのフォルダの容量: {} (ハードリンクを重複して数えない場合: {})",
+            logical, unique
+        )
+    },
+    dashboard: "統計",
+    dashboard_loading: "アーカイブ済みのログを数えています...",
+    dashboard_empty: "まだアーカイブされたログはありません",
+    dashboard_summary: |logs, first, last| format!("{}から{}までの{}件のログ", first, last, logs),
+    dashboard_columns: ["月", "ログ数", "容量"],
+    start_with_windows: "Windowsの起動時に開始",
+    open_diagnostics: "診断ログを開く",
+    copy_diagnostics: "診断情報をコピー",
+    diagnostics_copied: "診断情報をクリップボードにコピーしました。Issueに貼り付けてください",
+    self_test: "動作チェック",
+    self_test_checks: [
+        "LocalLowフォルダの取得",
+        "VRChatのログフォルダの読み取り",
+        "保存先フォルダの作成",
+        "保存先へのハードリンクの作成",
+    ],
+    self_test_passed: "ログのアーカイブに必要な機能はすべて使えます",
+    self_test_failed: "一部のチェックに失敗したため、ログが正しくアーカイブされない可能性があります",
+    settings_interval: "スキャン間隔 (秒)",
+    settings_collection_path: "保存先フォルダ (空欄で既定)",
+    settings_link_mode: "保存方法",
+    settings_retention_days: "保存期間 (日、空欄で無期限)",
+    settings_restart_note: "保存先フォルダの変更は再起動後に反映されます",
+    settings_save: "保存",
+    settings_invalid: "設定が正しくありません",
+    config_invalid_fallback: "ファイルが修正されるまで既定の設定で起動しますか？",
+    link_modes: ["ハードリンク", "コピー", "シンボリックリンク"],
+    welcome_message: |collection_path| {
+        format!(
+            "VRChatのログを{}に集めています。トレイのメニューからいつでも開けます",
+            collection_path
+        )
+    },
+    open_releases: "リリースページを開く",
+    update_menu: |tag| format!("{}をダウンロード", tag),
+    update_message: |tag| {
+        format!(
+            "{}が公開されています。トレイのメニューからダウンロードできます",
+            tag
+        )
+    },
+    exit: "終了",
+    exit_confirm: "VRCLogUnrotateを再び起動するまでログはアーカイブされません。終了しますか？",
+    session_summary: |archived| format!("今回の起動中に{}件のログをアーカイブしました", archived),
+    purge_confirm: |collection_path| {
+        format!(
+            "{}とアーカイブされたすべてのログを削除しますか？元に戻すことはできません",
+            collection_path
+        )
+    },
+    locallow_missing: "LocalLowフォルダの場所をWindowsから取得できなかったため、VRChatのログを探せません。%USERPROFILE%\\AppData\\LocalLowが存在し、ユーザープロファイルが正しく読み込まれているか確認してから、VRCLogUnrotateを再び起動してください",
+    error_title: "VRCLogUnrotateの動作中にエラーが発生しました",
+    errors_message: |first, count| format!("{} (他{}件のエラー)", first, count - 1),
+    crash_title: "VRCLogUnrotateがクラッシュしました",
+    crash_message: "回復できないエラーが発生したためVRCLogUnrotateを終了します",
+};
+
+impl Locale {
+    pub fn detect() -> Self {
+        let langid = unsafe { GetUserDefaultUILanguage() };
+        if PRIMARYLANGID(langid) == LANG_JAPANESE {
+            Locale::Ja
+        } else {
+            Locale::En
+        }
+    }
+
+    pub fn strings(self) -> &'static Strings {
+        match self {
+            Locale::En => &EN,
+            Locale::Ja => &JA,
+        }
+    }
+}
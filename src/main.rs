@@ -1,46 +1,174 @@
 #![windows_subsystem = "windows"]
 
-use std::cell::RefCell;
+use std::any::Any;
+use std::cell::{Cell, RefCell};
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+use std::convert::TryInto;
+use std::ffi::{OsStr, OsString};
 use std::fmt::Debug;
-use std::fs::{self, OpenOptions};
-use std::io::{self, Read};
+use std::fs::{self, File, FileTimes, OpenOptions};
+use std::io::{self, Read, Write};
+use std::mem;
+use std::ops::ControlFlow;
+use std::os::windows::fs::{FileTimesExt, OpenOptionsExt};
+use std::os::windows::io::AsRawHandle;
+use std::panic;
 use std::path::{Path, PathBuf};
-use std::str::FromStr;
-use std::sync::mpsc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{mpsc, Arc, Mutex, OnceLock};
 use std::thread;
-use std::time::Duration;
+use std::time::{Duration, Instant, SystemTime};
 
-use anyhow::Result;
-use chrono::{Datelike, NaiveDate};
+use anyhow::{Context, Result};
+use chrono::{
+    DateTime, Datelike, FixedOffset, Local, NaiveDate, NaiveDateTime, NaiveTime, TimeZone,
+};
+use flate2::{write::GzEncoder, Compression};
 use lazy_static::lazy_static;
 use nwd::NwgUi;
 use nwg::NativeUi;
-use regex::Regex;
+use regex::{Regex, RegexSet};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use winapi::shared::winerror::{
+    ERROR_DISK_FULL, ERROR_HANDLE_DISK_FULL, ERROR_NOT_SAME_DEVICE, ERROR_PRIVILEGE_NOT_HELD,
+    ERROR_SHARING_VIOLATION,
+};
 use winapi::um::{
     combaseapi::CoTaskMemFree,
+    fileapi::{GetFileInformationByHandle, BY_HANDLE_FILE_INFORMATION},
+    ioapiset::DeviceIoControl,
     knownfolders::FOLDERID_LocalAppDataLow,
     shellapi::ShellExecuteW,
     shlobj::{SHGetKnownFolderPath, KF_FLAG_DEFAULT},
-    winuser::SW_SHOWNORMAL,
+    winbase::FILE_FLAG_BACKUP_SEMANTICS,
+    wincon::{AttachConsole, ATTACH_PARENT_PROCESS},
+    winioctl::FSCTL_SET_COMPRESSION,
+    winnt::{
+        COMPRESSION_FORMAT_DEFAULT, FILE_READ_DATA, FILE_SHARE_DELETE, FILE_SHARE_READ,
+        FILE_SHARE_WRITE, FILE_WRITE_DATA,
+    },
+    winuser::{MessageBoxW, IDYES, MB_DEFBUTTON2, MB_ICONWARNING, MB_YESNO, SW_SHOWNORMAL},
 };
 
+mod archive_index;
+mod autostart;
+mod config;
+mod content_index;
+mod diagnostics;
+mod eventlog;
+mod filesystem;
+mod instance;
+mod locale;
+mod manifest;
+mod mmap;
+mod partition;
+mod process;
+mod scan;
+mod sqlite;
+mod state;
+mod update;
+mod watch;
+mod worker;
+mod zip;
+
+use archive_index::{ArchiveIndex, IndexEntry};
+use config::Config;
+use content_index::ContentIndex;
+use diagnostics::Diagnostics;
+use filesystem::{FileSystem, OsFileSystem};
+use instance::Instance;
+use locale::Locale;
+use manifest::{Manifest, ManifestEntry, Upsert};
+use partition::PartitionTemplate;
+use state::State;
+use watch::{DirectoryWatcher, Waker};
+use worker::WorkerContext;
+use zip::ZipWriter;
+
 const VENDOR_NAME: &str = "KOBA789";
 const APP_NAME: &str = "VRCLogUnrotate";
+const VRCHAT_EXE: &str = "VRChat.exe";
 
-struct CrashNotifier(Option<nwg::NoticeSender>);
-impl CrashNotifier {
-    fn disable(&mut self) {
-        self.0 = None;
-    }
+lazy_static! {
+    // for the crash report, as the panic payload itself is gone once the worker has unwound
+    static ref LAST_PANIC: Mutex<Option<String>> = Mutex::new(None);
+    /// Set in portable mode, chosen by `--portable` or a `portable.txt` beside the executable.
+    static ref PORTABLE_PATH: Option<PathBuf> = portable_path();
+}
+
+/// How logfiles are told by their name and header. Taken from the settings the first
+/// `Unrotate` is created with, so a change only applies after a restart.
+static LOG_PATTERNS: OnceLock<LogPatterns> = OnceLock::new();
+
+struct LogPatterns {
+    file_name: Regex,
+    header: regex::bytes::Regex,
 }
-impl Drop for CrashNotifier {
-    fn drop(&mut self) {
-        if let Some(sender) = self.0 {
-            sender.notice();
+
+impl LogPatterns {
+    fn from_config(config: &Config) -> Self {
+        Self {
+            file_name: config.filename_pattern(),
+            header: config.header_pattern(),
         }
     }
 }
 
+fn log_patterns() -> &'static LogPatterns {
+    LOG_PATTERNS.get_or_init(|| LogPatterns::from_config(&Config::default()))
+}
+
+fn portable_path() -> Option<PathBuf> {
+    let exe_path = std::env::current_exe().ok()?;
+    let exe_folder_path = exe_path.parent()?;
+    if std::env::args().skip(1).any(|arg| arg == "--portable")
+        || exe_folder_path.join("portable.txt").exists()
+    {
+        Some(exe_folder_path.to_owned())
+    } else {
+        None
+    }
+}
+
+/// Where the app keeps its own files: beside the executable in portable mode, and in its
+/// folder under LocalLow otherwise.
+fn app_data_path(locallow_path: &Path) -> PathBuf {
+    match *PORTABLE_PATH {
+        Some(ref portable_path) => portable_path.clone(),
+        None => locallow_path.join(VENDOR_NAME).join(APP_NAME),
+    }
+}
+
+/// How long exiting waits for the worker to finish the logfile at hand.
+const SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// How long the exit summary is shown for, as the balloon goes away along with the tray icon.
+const EXIT_SUMMARY_DURATION: Duration = Duration::from_secs(2);
+
+/// How many errors may wait for the UI before further ones are only logged.
+const ERROR_QUEUE_LEN: usize = 16;
+
+struct StepReport {
+    finished_at: DateTime<Local>,
+    linked: usize,
+}
+
+enum WorkerStatus {
+    /// How far a step has got through a backlog of logs it has not seen before.
+    Progress {
+        done: usize,
+        total: usize,
+    },
+    Finished(StepReport),
+}
+
+#[derive(Clone, Copy)]
+struct ScanStatus {
+    scanned_at: DateTime<Local>,
+    archived_today: usize,
+}
+
 #[derive(Default, NwgUi)]
 pub struct SystemTray {
     #[nwg_control]
@@ -53,6 +181,12 @@ pub struct SystemTray {
     #[nwg_resource(source_embed: Some(&data.embed), source_embed_str: Some("MAINICON"))]
     icon: nwg::Icon,
 
+    #[nwg_resource(source_embed: Some(&data.embed), source_embed_str: Some("ICON_PAUSED"))]
+    paused_icon: nwg::Icon,
+
+    #[nwg_resource(source_embed: Some(&data.embed), source_embed_str: Some("ICON_ERROR"))]
+    error_icon: nwg::Icon,
+
     #[nwg_control(icon: Some(&data.icon), tip: Some("VRCLogUnrotate"))]
     #[nwg_events(MousePressLeftUp: [SystemTray::show_menu], OnContextMenu: [SystemTray::show_menu])]
     tray: nwg::TrayNotification,
@@ -60,14 +194,93 @@ pub struct SystemTray {
     #[nwg_control(parent: window, popup: true)]
     tray_menu: nwg::Menu,
 
-    #[nwg_control(parent: tray_menu, text: "ログのフォルダを開く")]
+    #[nwg_control(parent: tray_menu, text: data.locale.strings().open_collection)]
     #[nwg_events(OnMenuItemSelected: [SystemTray::open_collection])]
     tray_item_open_collection: nwg::MenuItem,
 
+    #[nwg_control(parent: tray_menu, text: data.locale.strings().open_today)]
+    #[nwg_events(OnMenuItemSelected: [SystemTray::open_today])]
+    tray_item_open_today: nwg::MenuItem,
+
+    #[nwg_control(parent: tray_menu, text: data.locale.strings().recent)]
+    tray_recent: nwg::Menu,
+
+    #[nwg_control(parent: tray_recent, text: "-", disabled: true)]
+    #[nwg_events(OnMenuItemSelected: [SystemTray::open_recent(SELF, CTRL)])]
+    tray_item_recent0: nwg::MenuItem,
+
+    #[nwg_control(parent: tray_recent, text: "-", disabled: true)]
+    #[nwg_events(OnMenuItemSelected: [SystemTray::open_recent(SELF, CTRL)])]
+    tray_item_recent1: nwg::MenuItem,
+
+    #[nwg_control(parent: tray_recent, text: "-", disabled: true)]
+    #[nwg_events(OnMenuItemSelected: [SystemTray::open_recent(SELF, CTRL)])]
+    tray_item_recent2: nwg::MenuItem,
+
+    #[nwg_control(parent: tray_recent, text: "-", disabled: true)]
+    #[nwg_events(OnMenuItemSelected: [SystemTray::open_recent(SELF, CTRL)])]
+    tray_item_recent3: nwg::MenuItem,
+
+    #[nwg_control(parent: tray_recent, text: "-", disabled: true)]
+    #[nwg_events(OnMenuItemSelected: [SystemTray::open_recent(SELF, CTRL)])]
+    tray_item_recent4: nwg::MenuItem,
+
+    #[nwg_control(parent: tray_recent, text: "-", disabled: true)]
+    #[nwg_events(OnMenuItemSelected: [SystemTray::open_recent(SELF, CTRL)])]
+    tray_item_recent5: nwg::MenuItem,
+
+    #[nwg_control(parent: tray_recent, text: "-", disabled: true)]
+    #[nwg_events(OnMenuItemSelected: [SystemTray::open_recent(SELF, CTRL)])]
+    tray_item_recent6: nwg::MenuItem,
+
+    #[nwg_control(parent: tray_menu, text: data.locale.strings().pause)]
+    #[nwg_events(OnMenuItemSelected: [SystemTray::toggle_pause])]
+    tray_item_pause: nwg::MenuItem,
+
+    #[nwg_control(parent: tray_menu, text: data.locale.strings().scan_now)]
+    #[nwg_events(OnMenuItemSelected: [SystemTray::scan_now])]
+    tray_item_scan_now: nwg::MenuItem,
+
+    #[nwg_control(parent: tray_menu, text: data.locale.strings().settings)]
+    #[nwg_events(OnMenuItemSelected: [SystemTray::open_settings])]
+    tray_item_settings: nwg::MenuItem,
+
+    #[nwg_control(parent: tray_menu, text: data.locale.strings().export_archive)]
+    #[nwg_events(OnMenuItemSelected: [SystemTray::export_archive])]
+    tray_item_export: nwg::MenuItem,
+
+    #[nwg_control(parent: tray_menu, text: data.locale.strings().disk_usage)]
+    #[nwg_events(OnMenuItemSelected: [SystemTray::show_disk_usage])]
+    tray_item_disk_usage: nwg::MenuItem,
+
+    #[nwg_control(parent: tray_menu, text: data.locale.strings().dashboard)]
+    #[nwg_events(OnMenuItemSelected: [SystemTray::open_dashboard])]
+    tray_item_dashboard: nwg::MenuItem,
+
+    #[nwg_control(parent: tray_menu, text: data.locale.strings().start_with_windows)]
+    #[nwg_events(OnMenuItemSelected: [SystemTray::toggle_autostart])]
+    tray_item_autostart: nwg::MenuItem,
+
+    #[nwg_control(parent: tray_menu, text: data.locale.strings().open_diagnostics)]
+    #[nwg_events(OnMenuItemSelected: [SystemTray::open_diagnostics])]
+    tray_item_open_diagnostics: nwg::MenuItem,
+
+    #[nwg_control(parent: tray_menu, text: data.locale.strings().copy_diagnostics)]
+    #[nwg_events(OnMenuItemSelected: [SystemTray::copy_diagnostics])]
+    tray_item_copy_diagnostics: nwg::MenuItem,
+
+    #[nwg_control(parent: tray_menu, text: data.locale.strings().self_test)]
+    #[nwg_events(OnMenuItemSelected: [SystemTray::run_self_test])]
+    tray_item_self_test: nwg::MenuItem,
+
+    #[nwg_control(parent: tray_menu, text: data.locale.strings().open_releases)]
+    #[nwg_events(OnMenuItemSelected: [SystemTray::open_releases])]
+    tray_item_releases: nwg::MenuItem,
+
     #[nwg_control(parent: tray_menu)]
     tray_item_sep1: nwg::MenuSeparator,
 
-    #[nwg_control(parent: tray_menu, text: "終了")]
+    #[nwg_control(parent: tray_menu, text: data.locale.strings().exit)]
     #[nwg_events(OnMenuItemSelected: [SystemTray::exit])]
     tray_item_exit: nwg::MenuItem,
 
@@ -79,270 +292,4433 @@ pub struct SystemTray {
     #[nwg_events( OnNotice: [SystemTray::on_crash] )]
     crash_notice: nwg::Notice,
 
-    error_mpsc: RefCell<Option<mpsc::Receiver<anyhow::Error>>>,
-    collection_path: RefCell<Option<PathBuf>>,
+    #[nwg_control]
+    #[nwg_events( OnNotice: [SystemTray::on_status] )]
+    status_notice: nwg::Notice,
+
+    #[nwg_control]
+    #[nwg_events( OnNotice: [SystemTray::on_update_available] )]
+    update_notice: nwg::Notice,
+
+    #[nwg_control]
+    #[nwg_events( OnNotice: [SystemTray::on_task_done] )]
+    task_notice: nwg::Notice,
+
+    #[nwg_control]
+    #[nwg_events( OnNotice: [SystemTray::on_dashboard_loaded] )]
+    dashboard_notice: nwg::Notice,
+
+    // exits once the exit summary has been shown for a moment
+    #[nwg_control(parent: window, interval: EXIT_SUMMARY_DURATION, max_tick: Some(1))]
+    #[nwg_events( OnTimerTick: [SystemTray::quit] )]
+    exit_timer: nwg::AnimationTimer,
+
+    // another instance was started, see `Instance`
+    #[nwg_control]
+    #[nwg_events( OnNotice: [SystemTray::show_menu] )]
+    show_menu_notice: nwg::Notice,
+
+    #[nwg_resource(title: data.locale.strings().export_source_title, action: nwg::FileDialogAction::OpenDirectory)]
+    export_source_dialog: nwg::FileDialog,
+
+    #[nwg_resource(title: data.locale.strings().export_archive, action: nwg::FileDialogAction::Save, filters: "Zip(*.zip)")]
+    export_save_dialog: nwg::FileDialog,
+
+    #[nwg_control(size: (480, 220), center: true, title: data.locale.strings().settings, flags: "WINDOW")]
+    settings_window: nwg::Window,
+
+    #[nwg_layout(parent: settings_window, spacing: 2)]
+    settings_grid: nwg::GridLayout,
+
+    #[nwg_control(parent: settings_window, text: data.locale.strings().settings_interval)]
+    #[nwg_layout_item(layout: settings_grid, row: 0, col: 0)]
+    settings_interval_label: nwg::Label,
+
+    #[nwg_control(parent: settings_window)]
+    #[nwg_layout_item(layout: settings_grid, row: 0, col: 1)]
+    settings_interval_input: nwg::TextInput,
+
+    #[nwg_control(parent: settings_window, text: data.locale.strings().settings_collection_path)]
+    #[nwg_layout_item(layout: settings_grid, row: 1, col: 0)]
+    settings_collection_path_label: nwg::Label,
+
+    #[nwg_control(parent: settings_window)]
+    #[nwg_layout_item(layout: settings_grid, row: 1, col: 1)]
+    settings_collection_path_input: nwg::TextInput,
+
+    #[nwg_control(parent: settings_window, text: data.locale.strings().settings_link_mode)]
+    #[nwg_layout_item(layout: settings_grid, row: 2, col: 0)]
+    settings_link_mode_label: nwg::Label,
+
+    #[nwg_control(parent: settings_window, collection: data.locale.strings().link_modes.to_vec())]
+    #[nwg_layout_item(layout: settings_grid, row: 2, col: 1)]
+    settings_link_mode_input: nwg::ComboBox<&'static str>,
+
+    #[nwg_control(parent: settings_window, text: data.locale.strings().settings_retention_days)]
+    #[nwg_layout_item(layout: settings_grid, row: 3, col: 0)]
+    settings_retention_days_label: nwg::Label,
+
+    #[nwg_control(parent: settings_window)]
+    #[nwg_layout_item(layout: settings_grid, row: 3, col: 1)]
+    settings_retention_days_input: nwg::TextInput,
+
+    #[nwg_control(parent: settings_window, text: data.locale.strings().settings_restart_note)]
+    #[nwg_layout_item(layout: settings_grid, row: 4, col: 0, col_span: 2)]
+    settings_restart_note: nwg::Label,
+
+    #[nwg_control(parent: settings_window, text: data.locale.strings().settings_save)]
+    #[nwg_layout_item(layout: settings_grid, row: 5, col: 1)]
+    #[nwg_events(OnButtonClick: [SystemTray::save_settings])]
+    settings_save_button: nwg::Button,
+
+    #[nwg_control(size: (400, 360), center: true, title: data.locale.strings().dashboard, flags: "WINDOW")]
+    dashboard_window: nwg::Window,
+
+    #[nwg_layout(parent: dashboard_window, spacing: 2)]
+    dashboard_grid: nwg::GridLayout,
+
+    #[nwg_control(parent: dashboard_window)]
+    #[nwg_layout_item(layout: dashboard_grid, row: 0, col: 0)]
+    dashboard_summary: nwg::Label,
+
+    #[nwg_control(parent: dashboard_window, list_style: nwg::ListViewStyle::Detailed, ex_flags: nwg::ListViewExFlags::GRID | nwg::ListViewExFlags::FULL_ROW_SELECT)]
+    #[nwg_layout_item(layout: dashboard_grid, row: 1, col: 0, row_span: 7)]
+    #[nwg_events(OnListViewDoubleClick: [SystemTray::reveal_month(SELF, EVT_DATA)])]
+    dashboard_list: nwg::ListView,
+
+    locallow: PathBuf,
+    config: RefCell<Config>,
+    // lets the settings dialog hand updated settings to the worker
+    config_mpsc: RefCell<Option<mpsc::Sender<Config>>>,
+    locale: Locale,
+    diagnostics: Diagnostics,
+    paused: Arc<AtomicBool>,
+    error_mpsc: RefCell<Option<mpsc::Receiver<(anyhow::Error, NotificationLevel)>>>,
+    status_mpsc: RefCell<Option<mpsc::Receiver<WorkerStatus>>>,
+    last_status: Cell<Option<ScanStatus>>,
+    // shown instead of the last scan while working through a backlog
+    progress: Cell<Option<(usize, usize)>>,
+    // whether anything went wrong since the last step that finished cleanly
+    failing: Cell<bool>,
+    archived_this_session: Cell<usize>,
+    // when each balloon in the last minute was shown
+    recent_notifications: RefCell<VecDeque<Instant>>,
+    // balloons held back during quiet hours, with their title and flags
+    held_notifications: RefCell<VecDeque<(String, String, nwg::TrayNotificationFlags)>>,
+    // background tasks started from the menu report a message to show when done
+    task_tx: RefCell<Option<mpsc::Sender<Result<String>>>>,
+    task_mpsc: RefCell<Option<mpsc::Receiver<Result<String>>>>,
+    collection: RefCell<Option<UnrotateCollection>>,
+    update_mpsc: RefCell<Option<mpsc::Receiver<String>>>,
+    // cuts the worker's sleep short for a manual scan
+    waker: RefCell<Option<Arc<Waker>>>,
+    // for stopping the worker at a safe point on exit
+    worker: RefCell<Option<Arc<WorkerContext>>>,
+    dashboard_mpsc: RefCell<Option<mpsc::Receiver<io::Result<ArchiveStats>>>>,
+    // the first log of each row in the dashboard list
+    dashboard_logs: RefCell<Vec<PathBuf>>,
+    // what each item of the recent submenu opens, in order
+    recent_folders: RefCell<Vec<PathBuf>>,
 }
 
 impl SystemTray {
     fn init(&self) {
-        let crash_notifier = CrashNotifier(Some(self.crash_notice.sender()));
-        let unrotate = Unrotate::new().unwrap();
-        *self.collection_path.borrow_mut() = Some(unrotate.collection.collection_path.clone());
-        let (tx, rx) = mpsc::channel();
+        // bounded, as a storm of errors would only be squashed into one balloon anyway
+        let (tx, rx) = mpsc::sync_channel(ERROR_QUEUE_LEN);
         *self.error_mpsc.borrow_mut() = Some(rx);
         let error_notifier = self.error_notice.sender();
+        let (status_tx, status_rx) = mpsc::channel();
+        *self.status_mpsc.borrow_mut() = Some(status_rx);
+        let status_notifier = self.status_notice.sender();
+        let (task_tx, task_rx) = mpsc::channel();
+        *self.task_tx.borrow_mut() = Some(task_tx);
+        *self.task_mpsc.borrow_mut() = Some(task_rx);
+        let (config_tx, config_rx) = mpsc::channel();
+        *self.config_mpsc.borrow_mut() = Some(config_tx);
+        self.dashboard_list.set_headers_enabled(true);
+        for (index, &column) in self.locale.strings().dashboard_columns.iter().enumerate() {
+            self.dashboard_list.insert_column(column);
+            self.dashboard_list.set_column_width(index, 120);
+        }
+        let waker = Arc::new(Waker::new().expect("Failed to create the worker wake event"));
+        *self.waker.borrow_mut() = Some(waker.clone());
+        let config = self.config.borrow();
+        let unrotate = Unrotate::new(&self.locallow, &config);
+        *self.collection.borrow_mut() = Some(unrotate.collection.clone());
+        self.welcome_once(&unrotate.collection.collection_path);
+        if config.pin_to_quick_access {
+            self.pin_once(&unrotate.collection.collection_path);
+        }
+        if config.check_for_updates {
+            self.spawn_update_check();
+        }
+        let context = WorkerContext::new(
+            self.locallow.clone(),
+            config.clone(),
+            config_rx,
+            tx,
+            error_notifier,
+            status_tx,
+            status_notifier,
+            self.crash_notice.sender(),
+            self.paused.clone(),
+            waker,
+            self.diagnostics.clone(),
+        );
+        let context = Arc::new(context);
+        *self.worker.borrow_mut() = Some(context.clone());
+        worker::spawn(context, unrotate);
+    }
+
+    /// Tells a new user what the tray icon is for, once per user profile.
+    fn welcome_once(&self, collection_path: &Path) {
+        let welcomed_path = app_data_path(&self.locallow).join("welcomed");
+        if welcomed_path.exists() {
+            return;
+        }
+        let flags = nwg::TrayNotificationFlags::INFO_ICON | nwg::TrayNotificationFlags::LARGE_ICON;
+        let collection_path = collection_path.display().to_string();
+        self.notify(
+            NotificationLevel::Normal,
+            &(self.locale.strings().welcome_message)(&collection_path),
+            APP_NAME,
+            flags,
+        );
+        let result = fs::create_dir_all(welcomed_path.parent().unwrap())
+            .and_then(|_| File::create(&welcomed_path));
+        if let Err(e) = result {
+            self.diagnostics.log(format_args!(
+                "failed to create {}: {}",
+                welcomed_path.display(),
+                e
+            ));
+        }
+    }
+
+    /// Pins the collection to Explorer's Quick Access, unless this very folder has been
+    /// pinned before. Remembering that is what keeps unpinning by hand from being undone.
+    fn pin_once(&self, collection_path: &Path) {
+        let pinned_path = app_data_path(&self.locallow).join("pinned");
+        let collection = collection_path.to_string_lossy();
+        if fs::read_to_string(&pinned_path).is_ok_and(|pinned| pinned == collection) {
+            return;
+        }
+        // the verb needs an existing folder
+        if let Err(e) = fs::create_dir_all(collection_path) {
+            self.diagnostics.log(format_args!(
+                "failed to create {}: {}",
+                collection_path.display(),
+                e
+            ));
+            return;
+        }
+        if let Err(e) = shell_execute("pintohome", collection_path) {
+            // not worth a balloon, as the user did not ask for it just now
+            self.diagnostics.log(format_args!(
+                "failed to pin {}: {}",
+                collection_path.display(),
+                e
+            ));
+            return;
+        }
+        if let Err(e) = fs::write(&pinned_path, collection.as_bytes()) {
+            self.diagnostics.log(format_args!(
+                "failed to write {}: {}",
+                pinned_path.display(),
+                e
+            ));
+        }
+    }
+
+    /// Looks for a newer release at most once a day. Failures, e.g. when offline,
+    /// only go to the diagnostics log.
+    fn spawn_update_check(&self) {
+        let (tx, rx) = mpsc::channel();
+        *self.update_mpsc.borrow_mut() = Some(rx);
+        let notifier = self.update_notice.sender();
+        let stamp_path = app_data_path(&self.locallow).join("last_update_check");
+        let diagnostics = self.diagnostics.clone();
         thread::spawn(move || {
-            let mut crash_notifier = crash_notifier;
-            loop {
-                if let Err(e) = unrotate.step() {
-                    if tx.send(e).is_err() {
-                        break;
+            if !update::claim_daily_check(&stamp_path) {
+                return;
+            }
+            match update::latest_release_tag() {
+                Ok(tag) if update::is_newer(&tag, env!("CARGO_PKG_VERSION")) => {
+                    if tx.send(tag).is_ok() {
+                        notifier.notice();
                     }
-                    error_notifier.notice();
                 }
-                thread::sleep(Duration::from_secs(60));
+                Ok(_) => {}
+                Err(e) => diagnostics.log(format_args!("update check failed: {}", e)),
             }
-            crash_notifier.disable();
         });
     }
 
+    fn on_update_available(&self) {
+        if let Some(ref rx) = *self.update_mpsc.borrow() {
+            for tag in rx.try_iter() {
+                let strings = self.locale.strings();
+                set_menu_item_text(&self.tray_item_releases, &(strings.update_menu)(&tag));
+                let flags =
+                    nwg::TrayNotificationFlags::INFO_ICON | nwg::TrayNotificationFlags::LARGE_ICON;
+                self.notify(
+                    NotificationLevel::Normal,
+                    &(strings.update_message)(&tag),
+                    APP_NAME,
+                    flags,
+                );
+            }
+        }
+    }
+
+    fn open_releases(&self) {
+        let result = shell_execute("open", Path::new(update::RELEASES_PAGE));
+        if let Err(e) = result {
+            self.show_error(&anyhow::Error::new(e).context("failed to open the releases page"));
+        }
+    }
+
     fn show_menu(&self) {
+        let autostart = autostart::is_enabled().unwrap_or(false);
+        self.tray_item_autostart.set_checked(autostart);
+        self.update_recent();
         let (x, y) = nwg::GlobalCursor::position();
         self.tray_menu.popup(x, y);
     }
 
     fn open_collection(&self) {
-        if let Some(ref collection_path) = *self.collection_path.borrow() {
-            open_explore(&collection_path);
+        if let Some(ref collection) = *self.collection.borrow() {
+            self.explore(&collection.collection_path);
         }
     }
 
-    fn exit(&self) {
-        nwg::stop_thread_dispatch();
+    /// Opens `path` in Explorer, creating it first if need be, as the collection only
+    /// exists once something has been archived. Tells the user if that fails.
+    fn explore(&self, path: &Path) {
+        if let Err(e) = fs::create_dir_all(path).and_then(|()| open_explore(path)) {
+            self.show_error(
+                &anyhow::Error::new(e).context(format!("failed to open {}", path.display())),
+            );
+        }
     }
 
-    fn on_error(&self) {
-        if let Some(ref rx) = *self.error_mpsc.borrow() {
-            for e in rx.try_iter() {
-                let flags = nwg::TrayNotificationFlags::WARNING_ICON
-                    | nwg::TrayNotificationFlags::LARGE_ICON;
-                self.tray.show(
-                    &format!("{}", e),
-                    Some("VRCLogUnrotateの動作中にエラーが発生しました"),
-                    Some(flags),
-                    None,
-                );
+    fn open_today(&self) {
+        if let Some(ref collection) = *self.collection.borrow() {
+            // the partition dates are in `utc_offset_minutes`, if set
+            let today = local_date(Local::now(), self.config.borrow().utc_offset());
+            let partition_folder_path = collection.partition_folder_path(today);
+            if partition_folder_path.is_dir() {
+                self.explore(&partition_folder_path);
+            } else {
+                self.explore(&collection.collection_path);
             }
         }
     }
 
-    fn on_crash(&self) {
-        let flags = nwg::TrayNotificationFlags::ERROR_ICON | nwg::TrayNotificationFlags::LARGE_ICON;
-        self.tray.show(
-            "回復できないエラーが発生したためVRCLogUnrotateを終了します",
-            Some("VRCLogUnrotateがクラッシュしました"),
-            Some(flags),
-            None,
-        );
-    }
-}
-
-fn open_explore(path: &Path) {
-    use std::ffi::OsString;
-    use std::os::windows::ffi::OsStrExt;
-    use std::{iter, ptr};
-    #[allow(non_snake_case)]
-    let lpOperation: Vec<_> = OsString::from("explore".to_string())
-        .encode_wide()
-        .chain(iter::once(0))
-        .collect();
-    #[allow(non_snake_case)]
-    let lpFile: Vec<_> = path
-        .as_os_str()
-        .encode_wide()
-        .chain(iter::once(0))
-        .collect();
-    unsafe {
-        ShellExecuteW(
-            ptr::null_mut(),
-            lpOperation.as_ptr(),
-            lpFile.as_ptr(),
-            ptr::null(),
-            ptr::null(),
-            SW_SHOWNORMAL,
-        );
+    fn recent_items(&self) -> [&nwg::MenuItem; 7] {
+        [
+            &self.tray_item_recent0,
+            &self.tray_item_recent1,
+            &self.tray_item_recent2,
+            &self.tray_item_recent3,
+            &self.tray_item_recent4,
+            &self.tray_item_recent5,
+            &self.tray_item_recent6,
+        ]
     }
-}
-
-fn get_appdata_locallow() -> Option<PathBuf> {
-    use std::ffi::OsString;
-    use std::os::windows::prelude::OsStringExt;
-    use std::{ffi, ptr, slice};
 
-    let mut path = None;
-    unsafe {
-        let mut ppszpath = ptr::null_mut();
-        let ret = SHGetKnownFolderPath(
-            &FOLDERID_LocalAppDataLow,
-            KF_FLAG_DEFAULT,
-            ptr::null_mut(),
-            &mut ppszpath,
-        );
-        if ret == 0 {
-            let len = (0..).take_while(|&i| *ppszpath.offset(i) != 0).count();
-            let wide_slice = slice::from_raw_parts(ppszpath, len);
-            let osstring = OsString::from_wide(wide_slice);
-            path = Some(PathBuf::from(osstring));
+    /// Fills the recent submenu with the latest days that have logs. Menu items cannot be
+    /// hidden, so the slots left over are disabled instead.
+    fn update_recent(&self) {
+        let items = self.recent_items();
+        let recent = match *self.collection.borrow() {
+            Some(ref collection) => collection
+                .recent_partitions(items.len())
+                .unwrap_or_default(),
+            None => vec![],
+        };
+        for (index, item) in items.iter().enumerate() {
+            match recent.get(index) {
+                Some((date, _)) => {
+                    set_menu_item_text(item, &date.format("%Y-%m-%d").to_string());
+                    item.set_enabled(true);
+                }
+                None => {
+                    set_menu_item_text(item, "-");
+                    item.set_enabled(false);
+                }
+            }
         }
-        CoTaskMemFree(ppszpath as *mut ffi::c_void);
+        *self.recent_folders.borrow_mut() = recent.into_iter().map(|(_, path)| path).collect();
     }
-    path
-}
-
-struct LocalLowVRChat {
-    vrchat_path: PathBuf,
-}
 
-impl LocalLowVRChat {
-    fn new(vrchat_path: PathBuf) -> Self {
-        Self { vrchat_path }
+    fn open_recent(&self, item: &nwg::MenuItem) {
+        let index = match self.recent_items().iter().position(|&i| i == item) {
+            Some(index) => index,
+            None => return,
+        };
+        if let Some(path) = self.recent_folders.borrow().get(index) {
+            // removed by retention since the menu was shown, say, which is not to be undone
+            if let Err(e) = open_explore(path) {
+                self.show_error(
+                    &anyhow::Error::new(e).context(format!("failed to open {}", path.display())),
+                );
+            }
+        }
     }
 
-    fn from_locallow_path(locallow_path: &Path) -> Self {
-        let vrchat_path = locallow_path.join("VRChat").join("VRChat");
-        Self::new(vrchat_path)
+    fn open_diagnostics(&self) {
+        let path = self.diagnostics.path();
+        if let Err(e) = fs::metadata(path).and_then(|_| shell_execute("open", path)) {
+            self.show_error(
+                &anyhow::Error::new(e).context(format!("failed to open {}", path.display())),
+            );
+        }
     }
 
-    fn list_logfile_paths(&self) -> Result<Vec<PathBuf>> {
-        lazy_static! {
-            static ref RE: Regex =
-                Regex::new("^output_log_(\\d{2}-\\d{2}-\\d{2}|\\d{4}-\\d{2}-\\d{2}_\\d{2}-\\d{2}-\\d{2})\\.txt$")
-                    .unwrap();
-        }
-        self.vrchat_path
-            .read_dir()?
-            .filter_map(|dir_entry| {
-                dir_entry
-                    .map_err(anyhow::Error::new)
-                    .and_then(|dir_entry| {
-                        dir_entry.file_type().map_err(Into::into).map(|file_type| {
-                            if file_type.is_file() {
-                                dir_entry.file_name().to_str().and_then(|file_name| {
-                                    RE.is_match(file_name).then(|| dir_entry.path())
-                                })
-                            } else {
-                                None
-                            }
-                        })
-                    })
-                    .transpose()
-            })
-            .collect()
+    /// Puts what a bug report needs on the clipboard, nothing redacted.
+    fn copy_diagnostics(&self) {
+        // small enough to paste into an issue as is
+        const TAIL_LEN: u64 = 8 * 1024;
+        let config = self.config.borrow();
+        let mut report = format!(
+            "{} {}\nOS: {}\n",
+            APP_NAME,
+            env!("CARGO_PKG_VERSION"),
+            os_version().as_deref().unwrap_or("unknown")
+        );
+        for vrchat in LocalLowVRChat::from_config(&self.locallow, &config) {
+            report.push_str(&format!("VRChat logs: {}\n", vrchat.vrchat_path.display()));
+        }
+        if let Some(ref collection) = *self.collection.borrow() {
+            report.push_str(&format!(
+                "Collection: {}\nLink mode: {:?}\n",
+                collection.collection_path.display(),
+                collection.link_mode
+            ));
+        }
+        match self.diagnostics.tail(TAIL_LEN) {
+            Ok(tail) => report.push_str(&format!("\n```\n{}```\n", tail)),
+            Err(e) => report.push_str(&format!("\nfailed to read the diagnostics log: {}\n", e)),
+        }
+        nwg::Clipboard::set_data_text(&self.window, &report);
+        let flags = nwg::TrayNotificationFlags::INFO_ICON | nwg::TrayNotificationFlags::LARGE_ICON;
+        self.notify(
+            NotificationLevel::Normal,
+            self.locale.strings().diagnostics_copied,
+            APP_NAME,
+            flags,
+        );
     }
-}
-
-#[derive(Debug)]
-struct VRCLogfile {
-    path: PathBuf,
-    date: NaiveDate,
-}
 
-impl VRCLogfile {
-    fn new(path: PathBuf) -> io::Result<Option<Self>> {
-        lazy_static! {
-            static ref RE: regex::bytes::Regex = regex::bytes::Regex::new("(?m)^(?P<yyyy>\\d{4})\\.(?P<MM>\\d{2})\\.(?P<dd>\\d{2}) (?:\\d{2}):(?:\\d{2}):(?:\\d{2}) ").unwrap();
+    fn run_self_test(&self) {
+        let collection_path = match *self.collection.borrow() {
+            Some(ref collection) => collection.collection_path.clone(),
+            None => return,
+        };
+        let results = self_test(&self.locallow, &self.config.borrow(), &collection_path);
+        let strings = self.locale.strings();
+        let mut content = String::new();
+        for (check, result) in strings.self_test_checks.iter().zip(&results) {
+            match result {
+                Ok(()) => content.push_str(&format!("\u{2713} {}\n", check)),
+                Err(e) => content.push_str(&format!("\u{2717} {}: {:#}\n", check, e)),
+            }
         }
-        let mut file = OpenOptions::new()
-            .create(false)
-            .write(false)
-            .append(false)
-            .read(true)
-            .open(&path)?;
-        let mut head_buf = vec![0u8; 30];
-        file.read_exact(&mut head_buf)?;
-        let captures = if let Some(captures) = RE.captures(&head_buf) {
-            captures
+        let passed = results.iter().all(Result::is_ok);
+        content.push('\n');
+        content.push_str(if passed {
+            strings.self_test_passed
         } else {
-            return Ok(None);
+            strings.self_test_failed
+        });
+        let params = nwg::MessageParams {
+            title: APP_NAME,
+            content: &content,
+            buttons: nwg::MessageButtons::Ok,
+            icons: if passed {
+                nwg::MessageIcons::Info
+            } else {
+                nwg::MessageIcons::Warning
+            },
         };
-        fn parse<T>(bytes: &[u8]) -> T
-        where
-            T: FromStr,
-            T::Err: Debug,
-        {
-            std::str::from_utf8(bytes)
-                .expect("digits")
-                .parse()
-                .expect("digits")
-        }
-        let year: i32 = parse(captures.name("yyyy").unwrap().as_bytes());
-        let month: u32 = parse(captures.name("MM").unwrap().as_bytes());
-        let day: u32 = parse(captures.name("dd").unwrap().as_bytes());
-        let date = NaiveDate::from_ymd(year, month, day);
-        Ok(Some(Self { path, date }))
+        nwg::message(&params);
     }
-}
 
-struct UnrotateCollection {
-    collection_path: PathBuf,
-}
+    fn scan_now(&self) {
+        if let Some(ref waker) = *self.waker.borrow() {
+            waker.wake();
+        }
+    }
 
-impl UnrotateCollection {
-    fn new(collection_path: PathBuf) -> Self {
-        Self { collection_path }
+    fn toggle_pause(&self) {
+        let paused = !self.paused.load(Ordering::SeqCst);
+        self.paused.store(paused, Ordering::SeqCst);
+        let strings = self.locale.strings();
+        if paused {
+            set_menu_item_text(&self.tray_item_pause, strings.resume);
+        } else {
+            set_menu_item_text(&self.tray_item_pause, strings.pause);
+        }
+        self.update_tray();
     }
 
-    fn with_locallow_path(locallow_path: &Path) -> Self {
-        let collection_path = locallow_path.join(VENDOR_NAME).join(APP_NAME).join("Logs");
-        Self::new(collection_path)
+    /// Reflects the worker's state in the tray tooltip and icon.
+    fn update_tray(&self) {
+        let strings = self.locale.strings();
+        let paused = self.paused.load(Ordering::SeqCst);
+        if paused {
+            self.tray.set_icon(&self.paused_icon);
+        } else if self.failing.get() {
+            self.tray.set_icon(&self.error_icon);
+        } else {
+            self.tray.set_icon(&self.icon);
+        }
+        if paused {
+            self.tray.set_tip(strings.paused_tip);
+        } else if let Some((done, total)) = self.progress.get() {
+            self.tray.set_tip(&(strings.progress_tip)(done, total));
+        } else if let Some(status) = self.last_status.get() {
+            let scanned_at = status.scanned_at.format("%H:%M").to_string();
+            self.tray
+                .set_tip(&(strings.status_tip)(&scanned_at, status.archived_today));
+        } else {
+            self.tray.set_tip(APP_NAME);
+        }
     }
 
-    fn partition_folder_path(&self, date: NaiveDate) -> PathBuf {
-        self.collection_path
-            .join(format!("{:04}-{:02}", date.year(), date.month()))
-            .join(format!("{:02}", date.day()))
+    fn open_settings(&self) {
+        let config = self.config.borrow();
+        self.settings_interval_input
+            .set_text(&config.interval_secs.to_string());
+        let collection_path = config
+            .collection_path
+            .as_ref()
+            .map(|path| path.display().to_string())
+            .unwrap_or_default();
+        self.settings_collection_path_input
+            .set_text(&collection_path);
+        let link_mode_index = LinkMode::ALL
+            .iter()
+            .position(|&mode| mode == config.link_mode);
+        self.settings_link_mode_input.set_selection(link_mode_index);
+        let retention_days = config
+            .retention_days
+            .map(|days| days.to_string())
+            .unwrap_or_default();
+        self.settings_retention_days_input.set_text(&retention_days);
+        self.settings_window.set_visible(true);
+        self.settings_window.set_focus();
     }
 
-    fn create_link(&self, logfile: &VRCLogfile) -> io::Result<()> {
-        let partition_folder_path = self.partition_folder_path(logfile.date);
-        fs::create_dir_all(&partition_folder_path)?;
-        let new_link_path = partition_folder_path.join(logfile.path.file_name().unwrap());
-        match fs::hard_link(&logfile.path, &new_link_path) {
-            Ok(_) => Ok(()),
-            Err(e) => match e.kind() {
-                io::ErrorKind::AlreadyExists => Ok(()),
-                _ => Err(e),
-            },
+    fn read_settings(&self) -> Result<Config> {
+        let mut config = self.config.borrow().clone();
+        let interval_secs = self.settings_interval_input.text();
+        config.interval_secs = interval_secs
+            .trim()
+            .parse()
+            .with_context(|| format!("invalid interval: {}", interval_secs))?;
+        let collection_path = self.settings_collection_path_input.text();
+        config.collection_path = match collection_path.trim() {
+            "" => None,
+            collection_path => Some(PathBuf::from(collection_path)),
+        };
+        if let Some(index) = self.settings_link_mode_input.selection() {
+            config.link_mode = LinkMode::ALL[index];
         }
+        let retention_days = self.settings_retention_days_input.text();
+        config.retention_days = match retention_days.trim() {
+            "" => None,
+            days => Some(
+                days.parse()
+                    .with_context(|| format!("invalid retention days: {}", days))?,
+            ),
+        };
+        config.validate()?;
+        Ok(config)
     }
-}
-
-struct Unrotate {
-    vrchat: LocalLowVRChat,
-    collection: UnrotateCollection,
-}
 
-impl Unrotate {
-    fn step(&self) -> Result<()> {
-        for path in self.vrchat.list_logfile_paths()? {
-            if let Some(logfile) = VRCLogfile::new(path)? {
-                self.collection.create_link(&logfile)?;
+    fn save_settings(&self) {
+        let result = self.read_settings().and_then(|config| {
+            config.save(&Config::path(&self.locallow))?;
+            Ok(config)
+        });
+        match result {
+            Ok(config) => {
+                if let Some(ref tx) = *self.config_mpsc.borrow() {
+                    let _ = tx.send(config.clone());
+                }
+                *self.config.borrow_mut() = config;
+                self.settings_window.set_visible(false);
+            }
+            Err(e) => {
+                nwg::modal_error_message(
+                    &self.settings_window,
+                    self.locale.strings().settings_invalid,
+                    &format!("{:#}", e),
+                );
             }
         }
-        Ok(())
     }
 
-    fn new() -> Result<Self> {
-        let locallow = get_appdata_locallow()
-            .ok_or_else(|| anyhow::anyhow!("failed to get LocalAppDataLow path"))?;
-        let vrchat = LocalLowVRChat::from_locallow_path(&locallow);
-        let collection = UnrotateCollection::with_locallow_path(&locallow);
-        Ok(Self { vrchat, collection })
+    fn export_archive(&self) {
+        let collection = match *self.collection.borrow() {
+            Some(ref collection) => collection.clone(),
+            None => return,
+        };
+        let strings = self.locale.strings();
+        let _ = self
+            .export_source_dialog
+            .set_default_folder(&collection.collection_path.to_string_lossy());
+        if !self.export_source_dialog.run(Some(&self.window)) {
+            return;
+        }
+        let folder = match self.export_source_dialog.get_selected_item() {
+            Ok(folder) => PathBuf::from(folder),
+            Err(_) => return,
+        };
+        if !folder.starts_with(&collection.collection_path) {
+            self.show_error(&anyhow::anyhow!(
+                "{}: {}",
+                strings.export_outside_collection,
+                folder.display()
+            ));
+            return;
+        }
+        if !self.export_save_dialog.run(Some(&self.window)) {
+            return;
+        }
+        let zip_path = match self.export_save_dialog.get_selected_item() {
+            Ok(zip_path) => PathBuf::from(zip_path),
+            Err(_) => return,
+        };
+        self.spawn_task(move || {
+            let files = collection
+                .export_zip(&folder, &zip_path)
+                .with_context(|| format!("failed to export {}", zip_path.display()))?;
+            let zip_path = zip_path.display().to_string();
+            Ok((strings.exported_message)(&zip_path, files))
+        });
     }
-}
 
-fn main() {
-    nwg::init().expect("Failed to init Native Windows GUI");
-    let _ui = SystemTray::build_ui(Default::default()).expect("Failed to build UI");
-    nwg::dispatch_thread_events();
+    fn show_disk_usage(&self) {
+        let collection = match *self.collection.borrow() {
+            Some(ref collection) => collection.clone(),
+            None => return,
+        };
+        let strings = self.locale.strings();
+        self.spawn_task(move || {
+            let usage = collection.disk_usage().with_context(|| {
+                format!("failed to measure {}", collection.collection_path.display())
+            })?;
+            Ok((strings.disk_usage_message)(
+                &format_size(usage.logical),
+                &format_size(usage.unique),
+            ))
+        });
+    }
+
+    fn open_dashboard(&self) {
+        let collection = match *self.collection.borrow() {
+            Some(ref collection) => collection.clone(),
+            None => return,
+        };
+        self.dashboard_list.clear();
+        self.dashboard_logs.borrow_mut().clear();
+        self.dashboard_summary
+            .set_text(self.locale.strings().dashboard_loading);
+        self.dashboard_window.set_visible(true);
+        self.dashboard_window.set_focus();
+        // a fresh channel per load, so a slow earlier scan cannot overwrite a later one
+        let (tx, rx) = mpsc::channel();
+        *self.dashboard_mpsc.borrow_mut() = Some(rx);
+        let notifier = self.dashboard_notice.sender();
+        thread::spawn(move || {
+            if tx.send(collection.archive_stats()).is_ok() {
+                notifier.notice();
+            }
+        });
+    }
+
+    fn on_dashboard_loaded(&self) {
+        let stats = match *self.dashboard_mpsc.borrow() {
+            Some(ref rx) => match rx.try_recv() {
+                Ok(stats) => stats,
+                Err(_) => return,
+            },
+            None => return,
+        };
+        let strings = self.locale.strings();
+        let stats = match stats {
+            Ok(stats) => stats,
+            Err(e) => {
+                self.diagnostics
+                    .log(format_args!("error: failed to load statistics: {}", e));
+                self.dashboard_summary.set_text(&e.to_string());
+                return;
+            }
+        };
+        let summary = match (stats.months.first(), stats.months.last()) {
+            (Some(first), Some(last)) => (strings.dashboard_summary)(
+                stats.months.iter().map(|month| month.logs).sum(),
+                &first.month.format("%Y-%m").to_string(),
+                &last.month.format("%Y-%m").to_string(),
+            ),
+            _ => strings.dashboard_empty.to_owned(),
+        };
+        self.dashboard_summary.set_text(&summary);
+        for month in &stats.months {
+            self.dashboard_list.insert_items_row(
+                None,
+                &[
+                    month.month.format("%Y-%m").to_string(),
+                    month.logs.to_string(),
+                    format_size(month.size),
+                ],
+            );
+        }
+        *self.dashboard_logs.borrow_mut() = stats
+            .months
+            .into_iter()
+            .map(|month| month.first_log.1)
+            .collect();
+    }
+
+    /// Shows where a month starts, with its first log selected in Explorer.
+    fn reveal_month(&self, data: &nwg::EventData) {
+        let (row_index, _) = data.on_list_view_item_index();
+        if let Some(path) = self.dashboard_logs.borrow().get(row_index) {
+            if let Err(e) = reveal_file(path) {
+                self.show_error(
+                    &anyhow::Error::new(e).context(format!("failed to show {}", path.display())),
+                );
+            }
+        }
+    }
+
+    /// Runs `task` off the UI thread, since walking a large collection takes a while,
+    /// and shows the message it returns once done.
+    fn spawn_task<F>(&self, task: F)
+    where
+        F: FnOnce() -> Result<String> + Send + 'static,
+    {
+        let tx = match *self.task_tx.borrow() {
+            Some(ref tx) => tx.clone(),
+            None => return,
+        };
+        let notifier = self.task_notice.sender();
+        thread::spawn(move || {
+            if tx.send(task()).is_ok() {
+                notifier.notice();
+            }
+        });
+    }
+
+    fn on_task_done(&self) {
+        if let Some(ref rx) = *self.task_mpsc.borrow() {
+            for result in rx.try_iter() {
+                match result {
+                    Ok(message) => {
+                        let flags = nwg::TrayNotificationFlags::INFO_ICON
+                            | nwg::TrayNotificationFlags::LARGE_ICON;
+                        self.notify(NotificationLevel::Normal, &message, APP_NAME, flags);
+                    }
+                    Err(e) => {
+                        self.diagnostics.log(format_args!("error: {:#}", e));
+                        self.show_error(&e);
+                    }
+                }
+            }
+        }
+    }
+
+    fn toggle_autostart(&self) {
+        let result = if self.tray_item_autostart.checked() {
+            autostart::disable()
+        } else {
+            autostart::enable()
+        };
+        if let Err(e) = result {
+            self.show_error(&e.into());
+        }
+    }
+
+    fn exit(&self) {
+        if self.config.borrow().confirm_exit {
+            let params = nwg::MessageParams {
+                title: APP_NAME,
+                content: self.locale.strings().exit_confirm,
+                buttons: nwg::MessageButtons::YesNo,
+                icons: nwg::MessageIcons::Question,
+            };
+            if nwg::message(&params) != nwg::MessageChoice::Yes {
+                return;
+            }
+        }
+        if let Some(ref worker) = *self.worker.borrow() {
+            if !worker.shut_down(SHUTDOWN_TIMEOUT) {
+                self.diagnostics
+                    .log(format_args!("worker still busy on exit, leaving it behind"));
+            }
+        }
+        // nothing held back is shown, as there is no later to hold it for
+        if self.config.borrow().exit_summary
+            && self.config.borrow().notification_level >= NotificationLevel::Normal
+            && !self.in_quiet_hours()
+        {
+            // count what finished since the last notice too
+            self.on_status();
+            let flags =
+                nwg::TrayNotificationFlags::INFO_ICON | nwg::TrayNotificationFlags::LARGE_ICON;
+            let text = (self.locale.strings().session_summary)(self.archived_this_session.get());
+            // bypasses the rate limit like `on_crash`, as nothing else will follow
+            self.tray.show(&text, Some(APP_NAME), Some(flags), None);
+            // keeps the messages flowing meanwhile, unlike sleeping
+            self.exit_timer.start();
+            return;
+        }
+        self.quit();
+    }
+
+    fn quit(&self) {
+        nwg::stop_thread_dispatch();
+    }
+
+    /// Shows a balloon unless `max_notifications_per_minute` have already been shown
+    /// in the last minute.
+    /// Only shows it at all if `notification_level` is at least `level`.
+    fn notify(
+        &self,
+        level: NotificationLevel,
+        text: &str,
+        title: &str,
+        flags: nwg::TrayNotificationFlags,
+    ) {
+        const WINDOW: Duration = Duration::from_secs(60);
+        if self.config.borrow().notification_level < level {
+            self.diagnostics.log(format_args!(
+                "notification not shown at this level: {}",
+                text
+            ));
+            return;
+        }
+        let limit = self.config.borrow().max_notifications_per_minute as usize;
+        if self.in_quiet_hours() {
+            if !self.config.borrow().quiet_hours_hold {
+                self.diagnostics.log(format_args!(
+                    "notification dropped in quiet hours: {}",
+                    text
+                ));
+                return;
+            }
+            // no more than could be shown at once afterwards
+            let mut held = self.held_notifications.borrow_mut();
+            if held.len() >= limit {
+                if let Some((text, _, _)) = held.pop_front() {
+                    self.diagnostics
+                        .log(format_args!("notification suppressed: {}", text));
+                }
+            }
+            held.push_back((text.to_string(), title.to_string(), flags));
+            return;
+        }
+        let mut recent = self.recent_notifications.borrow_mut();
+        let now = Instant::now();
+        while recent
+            .front()
+            .is_some_and(|&shown_at| now.duration_since(shown_at) >= WINDOW)
+        {
+            recent.pop_front();
+        }
+        if recent.len() >= limit {
+            self.diagnostics
+                .log(format_args!("notification suppressed: {}", text));
+            return;
+        }
+        recent.push_back(now);
+        self.tray.show(text, Some(title), Some(flags), None);
+    }
+
+    fn in_quiet_hours(&self) -> bool {
+        self.config
+            .borrow()
+            .quiet_hours()
+            .is_some_and(|quiet_hours| quiet_hours.contains(Local::now().time()))
+    }
+
+    /// Shows what was held back during quiet hours, once they are over.
+    fn release_held_notifications(&self) {
+        if self.in_quiet_hours() {
+            return;
+        }
+        let held: Vec<_> = self.held_notifications.borrow_mut().drain(..).collect();
+        for (text, title, flags) in held {
+            // already past the level check
+            self.notify(NotificationLevel::Silent, &text, &title, flags);
+        }
+    }
+
+    fn show_error(&self, e: &anyhow::Error) {
+        self.show_error_at(NotificationLevel::ErrorsOnly, e);
+    }
+
+    fn show_error_at(&self, level: NotificationLevel, e: &anyhow::Error) {
+        let flags =
+            nwg::TrayNotificationFlags::WARNING_ICON | nwg::TrayNotificationFlags::LARGE_ICON;
+        // with the cause, as the context alone rarely says what to do about it
+        self.notify(
+            level,
+            &format!("{:#}", e),
+            self.locale.strings().error_title,
+            flags,
+        );
+    }
+
+    fn on_error(&self) {
+        let errors: Vec<_> = match *self.error_mpsc.borrow() {
+            Some(ref rx) => rx.try_iter().collect(),
+            None => return,
+        };
+        if !errors.is_empty() {
+            // a failed step never finishes its progress, nor does it need to be shown any longer
+            self.progress.set(None);
+            self.failing.set(true);
+            self.update_tray();
+        }
+        // several errors pushed at once are usually one problem, so they share a balloon,
+        // shown if the most serious of them would be
+        let level = match errors.iter().map(|&(_, level)| level).min() {
+            Some(level) => level,
+            None => return,
+        };
+        match errors.len() {
+            1 => self.show_error_at(level, &errors[0].0),
+            count => {
+                let message =
+                    (self.locale.strings().errors_message)(&format!("{:#}", errors[0].0), count);
+                self.show_error_at(level, &anyhow::anyhow!(message));
+            }
+        }
+    }
+
+    fn on_status(&self) {
+        // after each scan, which is as often as anything new would be shown anyway
+        self.release_held_notifications();
+        if let Some(ref rx) = *self.status_mpsc.borrow() {
+            for status in rx.try_iter() {
+                let report = match status {
+                    WorkerStatus::Progress { done, total } => {
+                        self.progress.set(Some((done, total)));
+                        continue;
+                    }
+                    WorkerStatus::Finished(report) => report,
+                };
+                self.progress.set(None);
+                // any warnings of the step follow on the error channel
+                self.failing.set(false);
+                if report.linked > 0 {
+                    // `notify_on_archive` predates the levels, and asks for just these
+                    let level = if self.config.borrow().notify_on_archive {
+                        NotificationLevel::Normal
+                    } else {
+                        NotificationLevel::Verbose
+                    };
+                    let flags = nwg::TrayNotificationFlags::INFO_ICON
+                        | nwg::TrayNotificationFlags::LARGE_ICON;
+                    self.notify(
+                        level,
+                        &(self.locale.strings().archived_message)(report.linked),
+                        APP_NAME,
+                        flags,
+                    );
+                }
+                self.archived_this_session
+                    .set(self.archived_this_session.get() + report.linked);
+                let today = report.finished_at.naive_local().date();
+                let archived_today = match self.last_status.get() {
+                    Some(status) if status.scanned_at.naive_local().date() == today => {
+                        status.archived_today + report.linked
+                    }
+                    _ => report.linked,
+                };
+                self.last_status.set(Some(ScanStatus {
+                    scanned_at: report.finished_at,
+                    archived_today,
+                }));
+            }
+        }
+        self.update_tray();
+    }
+
+    fn on_crash(&self) {
+        let description = LAST_PANIC
+            .lock()
+            .ok()
+            .and_then(|last_panic| last_panic.clone())
+            .unwrap_or_else(|| "the worker thread stopped unexpectedly".to_string());
+        eventlog::report_error(&format!("{} crashed: {}", APP_NAME, description));
+        self.failing.set(true);
+        self.update_tray();
+        if self.config.borrow().notification_level < NotificationLevel::ErrorsOnly
+            || (self.in_quiet_hours() && !self.config.borrow().quiet_hours_crashes)
+        {
+            return;
+        }
+        let flags = nwg::TrayNotificationFlags::ERROR_ICON | nwg::TrayNotificationFlags::LARGE_ICON;
+        let strings = self.locale.strings();
+        // bypasses the rate limit, as this is the last thing the user will hear from us
+        self.tray.show(
+            strings.crash_message,
+            Some(strings.crash_title),
+            Some(flags),
+            None,
+        );
+    }
+}
+
+/// The date `datetime` falls on in `utc_offset`, or in the system time zone.
+fn local_date(datetime: DateTime<Local>, utc_offset: Option<FixedOffset>) -> NaiveDate {
+    match utc_offset {
+        Some(offset) => datetime.with_timezone(&offset).naive_local().date(),
+        None => datetime.naive_local().date(),
+    }
+}
+
+/// Formats a byte count with a binary unit, e.g. `1.5 GiB`.
+fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 5] = ["B", "KiB", "MiB", "GiB", "TiB"];
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{} {}", bytes, UNITS[0])
+    } else {
+        format!("{:.1} {}", size, UNITS[unit])
+    }
+}
+
+/// Grows the delay exponentially with the number of consecutive failures, up to 15 minutes.
+fn backoff(interval: Duration, failures: u32) -> Duration {
+    const MAX_BACKOFF: Duration = Duration::from_secs(15 * 60);
+    let factor = 1u32 << failures.min(16);
+    interval
+        .saturating_mul(factor)
+        .min(MAX_BACKOFF.max(interval))
+}
+
+fn panic_message(payload: &(dyn Any + Send)) -> &str {
+    if let Some(message) = payload.downcast_ref::<&str>() {
+        message
+    } else if let Some(message) = payload.downcast_ref::<String>() {
+        message
+    } else {
+        "unknown panic"
+    }
+}
+
+/// e.g. `10.0.22631.4169 (23H2)`, as the version APIs report whatever the manifest claims.
+fn os_version() -> Option<String> {
+    use std::ffi::OsStr;
+    use std::os::windows::ffi::OsStrExt;
+    use std::{iter, mem, ptr};
+    use winapi::shared::minwindef::DWORD;
+    use winapi::shared::winerror::ERROR_SUCCESS;
+    use winapi::um::winreg::{RegGetValueW, HKEY_LOCAL_MACHINE, RRF_RT_REG_DWORD, RRF_RT_REG_SZ};
+
+    let to_wide =
+        |s: &str| -> Vec<u16> { OsStr::new(s).encode_wide().chain(iter::once(0)).collect() };
+    let sub_key = to_wide("SOFTWARE\\Microsoft\\Windows NT\\CurrentVersion");
+    let get_dword = |name: &str| -> Option<DWORD> {
+        let name = to_wide(name);
+        let mut value: DWORD = 0;
+        let mut len = mem::size_of::<DWORD>() as DWORD;
+        let status = unsafe {
+            RegGetValueW(
+                HKEY_LOCAL_MACHINE,
+                sub_key.as_ptr(),
+                name.as_ptr(),
+                RRF_RT_REG_DWORD,
+                ptr::null_mut(),
+                &mut value as *mut DWORD as *mut _,
+                &mut len,
+            )
+        };
+        (status as DWORD == ERROR_SUCCESS).then_some(value)
+    };
+    let get_string = |name: &str| -> Option<String> {
+        let name = to_wide(name);
+        let mut buf = [0u16; 64];
+        let mut len = mem::size_of_val(&buf) as DWORD;
+        let status = unsafe {
+            RegGetValueW(
+                HKEY_LOCAL_MACHINE,
+                sub_key.as_ptr(),
+                name.as_ptr(),
+                RRF_RT_REG_SZ,
+                ptr::null_mut(),
+                buf.as_mut_ptr() as *mut _,
+                &mut len,
+            )
+        };
+        if status as DWORD != ERROR_SUCCESS {
+            return None;
+        }
+        let end = buf.iter().position(|&c| c == 0).unwrap_or(buf.len());
+        Some(String::from_utf16_lossy(&buf[..end]))
+    };
+    let mut version = format!(
+        "{}.{}.{}",
+        get_dword("CurrentMajorVersionNumber")?,
+        get_dword("CurrentMinorVersionNumber")?,
+        get_string("CurrentBuild")?
+    );
+    if let Some(ubr) = get_dword("UBR") {
+        version.push_str(&format!(".{}", ubr));
+    }
+    if let Some(display_version) = get_string("DisplayVersion") {
+        version.push_str(&format!(" ({})", display_version));
+    }
+    Some(version)
+}
+
+fn set_menu_item_text(item: &nwg::MenuItem, text: &str) {
+    use std::ffi::OsStr;
+    use std::os::windows::ffi::OsStrExt;
+    use std::{iter, mem};
+    use winapi::shared::minwindef::FALSE;
+    use winapi::um::winuser::{SetMenuItemInfoW, MENUITEMINFOW, MIIM_STRING};
+
+    let (menu, id) = item.handle.hmenu_item().expect("menu item is not bound");
+    let mut text: Vec<_> = OsStr::new(text)
+        .encode_wide()
+        .chain(iter::once(0))
+        .collect();
+    unsafe {
+        let mut info: MENUITEMINFOW = mem::zeroed();
+        info.cbSize = mem::size_of::<MENUITEMINFOW>() as u32;
+        info.fMask = MIIM_STRING;
+        info.dwTypeData = text.as_mut_ptr();
+        SetMenuItemInfoW(menu, id, FALSE, &info);
+    }
+}
+
+fn open_explore(path: &Path) -> io::Result<()> {
+    shell_execute("explore", path)
+}
+
+/// `ShellExecuteW` returns a value above 32 on success, or else one of the `SE_ERR_*`
+/// codes, the lower ones of which are Win32 error codes as well.
+fn check_shell_execute(instance: winapi::shared::minwindef::HINSTANCE) -> io::Result<()> {
+    match instance as isize {
+        code if code > 32 => Ok(()),
+        code @ (2 | 3 | 5 | 8 | 11) => Err(io::Error::from_raw_os_error(code as i32)),
+        // e.g. no application associated with the file
+        code => Err(io::Error::other(format!(
+            "ShellExecute failed with code {}",
+            code
+        ))),
+    }
+}
+
+/// Opens the folder containing `path` with `path` selected.
+fn reveal_file(path: &Path) -> io::Result<()> {
+    use std::ffi::OsString;
+    use std::os::windows::ffi::OsStrExt;
+    use std::{iter, ptr};
+    let to_wide = |s: &OsStr| -> Vec<u16> { s.encode_wide().chain(iter::once(0)).collect() };
+    let mut parameters = OsString::from("/select,\"");
+    parameters.push(path);
+    parameters.push("\"");
+    let file = to_wide(OsStr::new("explorer.exe"));
+    let parameters = to_wide(&parameters);
+    // explorer.exe starts even for a missing file, so `path` is checked beforehand
+    fs::metadata(path)?;
+    check_shell_execute(unsafe {
+        ShellExecuteW(
+            ptr::null_mut(),
+            ptr::null(),
+            file.as_ptr(),
+            parameters.as_ptr(),
+            ptr::null(),
+            SW_SHOWNORMAL,
+        )
+    })
+}
+
+fn shell_execute(operation: &str, path: &Path) -> io::Result<()> {
+    use std::ffi::OsString;
+    use std::os::windows::ffi::OsStrExt;
+    use std::{iter, ptr};
+    #[allow(non_snake_case)]
+    let lpOperation: Vec<_> = OsString::from(operation.to_string())
+        .encode_wide()
+        .chain(iter::once(0))
+        .collect();
+    #[allow(non_snake_case)]
+    let lpFile: Vec<_> = path
+        .as_os_str()
+        .encode_wide()
+        .chain(iter::once(0))
+        .collect();
+    check_shell_execute(unsafe {
+        ShellExecuteW(
+            ptr::null_mut(),
+            lpOperation.as_ptr(),
+            lpFile.as_ptr(),
+            ptr::null(),
+            ptr::null(),
+            SW_SHOWNORMAL,
+        )
+    })
+}
+
+fn get_appdata_locallow() -> Option<PathBuf> {
+    use std::ffi::OsString;
+    use std::os::windows::prelude::OsStringExt;
+    use std::{ffi, ptr, slice};
+
+    let mut path = None;
+    unsafe {
+        let mut ppszpath = ptr::null_mut();
+        let ret = SHGetKnownFolderPath(
+            &FOLDERID_LocalAppDataLow,
+            KF_FLAG_DEFAULT,
+            ptr::null_mut(),
+            &mut ppszpath,
+        );
+        if ret == 0 {
+            let len = (0..).take_while(|&i| *ppszpath.offset(i) != 0).count();
+            let wide_slice = slice::from_raw_parts(ppszpath, len);
+            let osstring = OsString::from_wide(wide_slice);
+            path = Some(PathBuf::from(osstring));
+        }
+        CoTaskMemFree(ppszpath as *mut ffi::c_void);
+    }
+    path
+}
+
+struct LocalLowVRChat {
+    vrchat_path: PathBuf,
+}
+
+impl LocalLowVRChat {
+    fn new(vrchat_path: PathBuf) -> Self {
+        Self { vrchat_path }
+    }
+
+    fn from_locallow_path(locallow_path: &Path) -> Self {
+        let vrchat_path = locallow_path.join("VRChat").join("VRChat");
+        Self::new(vrchat_path)
+    }
+
+    /// The sources to archive from, as set in `config`.
+    fn from_config(locallow_path: &Path, config: &Config) -> Vec<Self> {
+        if !config.vrchat_log_dirs.is_empty() {
+            config
+                .vrchat_log_dirs
+                .iter()
+                .cloned()
+                .map(Self::new)
+                .collect()
+        } else {
+            match config.vrchat_log_dir {
+                Some(ref vrchat_log_dir) => vec![Self::new(vrchat_log_dir.clone())],
+                None => vec![Self::from_locallow_path(locallow_path)],
+            }
+        }
+    }
+
+    /// Leaves out files whose name matches `exclude`, handing them to `excluded` instead.
+    fn list_logfile_paths(
+        &self,
+        filesystem: &dyn FileSystem,
+        exclude: &RegexSet,
+        mut excluded: impl FnMut(PathBuf),
+    ) -> Result<Vec<PathBuf>> {
+        let file_name_re = &log_patterns().file_name;
+        let mut paths = vec![];
+        for path in filesystem.list_files(&self.vrchat_path)? {
+            let file_name = match path.file_name().and_then(OsStr::to_str) {
+                Some(file_name) => file_name,
+                None => continue,
+            };
+            if !file_name_re.is_match(file_name) {
+                continue;
+            }
+            if exclude.is_match(file_name) {
+                excluded(path);
+            } else {
+                paths.push(path);
+            }
+        }
+        Ok(paths)
+    }
+}
+
+const HEADER_SCAN_LEN: usize = 4096;
+
+/// The folder in the collection that `flat_mirror` links every log into.
+const FLAT_FOLDER: &str = "_flat";
+
+/// Empty folders in the collection modified within this are left alone by maintenance.
+const EMPTY_FOLDER_MIN_AGE: Duration = Duration::from_secs(10 * 60);
+
+/// The folder within a partition that crash dumps are archived into.
+const CRASH_DUMPS_FOLDER: &str = "Crashes";
+/// The folder within a partition that `merge_split_logs` writes into.
+const MERGED_FOLDER: &str = "Merged";
+/// Gives the crash handler time to finish writing a dump before it is copied.
+const CRASH_DUMP_MIN_AGE: Duration = Duration::from_secs(60);
+
+#[derive(Debug)]
+struct VRCLogfile {
+    path: PathBuf,
+    /// The local date and time of the first line, as written by VRChat, i.e. when the
+    /// session started.
+    timestamp: NaiveDateTime,
+    /// The client build, if found in the header.
+    version: Option<String>,
+    first_line_hash: u64,
+}
+
+/// Tells logfiles apart by their content rather than their name, which VRChat may change.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize, Serialize)]
+struct LogIdentity {
+    first_timestamp: NaiveDateTime,
+    first_line_hash: u64,
+}
+
+impl VRCLogfile {
+    fn identity(&self) -> LogIdentity {
+        LogIdentity {
+            first_timestamp: self.timestamp,
+            first_line_hash: self.first_line_hash,
+        }
+    }
+
+    fn date(&self) -> NaiveDate {
+        self.timestamp.date()
+    }
+
+    fn open(path: &Path) -> io::Result<File> {
+        OpenOptions::new()
+            .create(false)
+            .write(false)
+            .append(false)
+            .read(true)
+            // VRChat keeps the current log open while it is running
+            .share_mode(FILE_SHARE_READ | FILE_SHARE_WRITE | FILE_SHARE_DELETE)
+            .open(path)
+    }
+
+    /// Scans the log line by line, up to `max_bytes` into it, see `scan::for_each_line`.
+    fn contains_match(
+        &self,
+        fs: &dyn FileSystem,
+        re: &regex::bytes::Regex,
+        max_bytes: Option<u64>,
+    ) -> io::Result<bool> {
+        let mut found = false;
+        scan::for_each_line(fs.open(&self.path)?, max_bytes, |line| {
+            found = re.is_match(line);
+            if found {
+                ControlFlow::Break(())
+            } else {
+                ControlFlow::Continue(())
+            }
+        })?;
+        Ok(found)
+    }
+
+    /// The name of the first world joined, or its `wrld_` ID if the name never appears.
+    /// Scans the log like `contains_match`.
+    fn first_world(
+        &self,
+        fs: &dyn FileSystem,
+        max_bytes: Option<u64>,
+    ) -> io::Result<Option<String>> {
+        lazy_static! {
+            static ref RE: regex::bytes::Regex = regex::bytes::Regex::new(
+                "\\[Behaviour\\] (?:Entering Room: (?P<name>[^\\r\\n]+)|Joining (?P<id>wrld_[0-9A-Za-z-]+))"
+            )
+            .unwrap();
+        }
+        let mut world_name = None;
+        let mut world_id = None;
+        scan::for_each_line(fs.open(&self.path)?, max_bytes, |line| {
+            let captures = match RE.captures(line) {
+                Some(captures) => captures,
+                None => return ControlFlow::Continue(()),
+            };
+            if let Some(name) = captures.name("name") {
+                world_name = Some(String::from_utf8_lossy(name.as_bytes()).trim().to_string());
+                return ControlFlow::Break(());
+            }
+            if world_id.is_none() {
+                world_id = Some(String::from_utf8_lossy(&captures["id"]).into_owned());
+            }
+            ControlFlow::Continue(())
+        })?;
+        Ok(world_name.or(world_id))
+    }
+
+    /// With `mmap`, the header is scanned in a memory-mapped view rather than read into a
+    /// buffer, falling back to reading if the file cannot be mapped (e.g. it is still empty).
+    /// A header whose date does not exist fails with `InvalidData`.
+    fn new(fs: &dyn FileSystem, path: PathBuf, mmap: bool) -> io::Result<Option<Self>> {
+        // the header is usually the very first line, but allow for a BOM or blank lines before it.
+        // a freshly created log may not contain the whole header yet
+        let head = match fs.read_head(&path, HEADER_SCAN_LEN, mmap) {
+            Ok(head) => head,
+            // retried on the next poll. a denied access is left to the caller, as
+            // it may be down to the file's ACL, which no amount of retrying fixes
+            Err(e) if e.raw_os_error() == Some(ERROR_SHARING_VIOLATION as i32) => return Ok(None),
+            Err(e) => return Err(e),
+        };
+        let head_buf: &[u8] = &head;
+        let captures = if let Some(captures) = config::find_header(&log_patterns().header, head_buf)
+        {
+            captures
+        } else {
+            return Ok(None);
+        };
+        let first_line = &head_buf[captures.get(0).unwrap().start()..];
+        let first_line = match first_line.iter().position(|&b| b == b'\n') {
+            Some(end) => &first_line[..end],
+            // a partial line would give the log another identity once it is complete
+            None => return Ok(None),
+        };
+        // persisted in the state, so it has to be stable across builds
+        let digest = Sha256::digest(first_line);
+        let first_line_hash = u64::from_le_bytes(digest[..8].try_into().unwrap());
+        // always digits with the built-in pattern, but not necessarily with `header_pattern`
+        let parse = |name: &str| {
+            captures
+                .name(name)
+                .and_then(|m| std::str::from_utf8(m.as_bytes()).ok())
+                .and_then(|digits| digits.parse::<u32>().ok())
+        };
+        let date =
+            (|| NaiveDate::from_ymd_opt(parse("yyyy")? as i32, parse("MM")?, parse("dd")?))();
+        let time = (|| NaiveTime::from_hms_opt(parse("hh")?, parse("mm")?, parse("ss")?))();
+        let timestamp = match (date, time) {
+            (Some(date), Some(time)) => date.and_time(time),
+            _ => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    "invalid date in log header",
+                ))
+            }
+        };
+        // e.g. `VRChat Build: 2024.1.1p2-1407--Release`; absent in some older clients
+        lazy_static! {
+            static ref VERSION_RE: regex::bytes::Regex =
+                regex::bytes::Regex::new("VRChat Build: *(?P<version>[^\\r\\n]+)").unwrap();
+        }
+        let version = VERSION_RE.captures(head_buf).map(|captures| {
+            String::from_utf8_lossy(captures.name("version").unwrap().as_bytes())
+                .trim_end()
+                .to_string()
+        });
+        Ok(Some(Self {
+            path,
+            timestamp,
+            version,
+            first_line_hash,
+        }))
+    }
+}
+
+/// Written next to each archived logfile as `<name>.meta`.
+#[derive(Serialize)]
+struct LogfileMeta<'a> {
+    date: NaiveDate,
+    original_path: &'a Path,
+    version: Option<&'a str>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LinkMode {
+    #[default]
+    HardLink,
+    Copy,
+    Symlink,
+    /// Takes logs out of VRChat's folder once their session has ended.
+    /// Only available through the config file, as the originals are gone afterwards.
+    Move,
+}
+
+impl LinkMode {
+    /// The modes offered in the settings dialog.
+    const ALL: [LinkMode; 3] = [LinkMode::HardLink, LinkMode::Copy, LinkMode::Symlink];
+}
+
+/// Which balloons are shown, each level adding to the ones before it.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NotificationLevel {
+    Silent,
+    /// Errors that stop logs from being archived, and crashes.
+    #[default]
+    ErrorsOnly,
+    /// Also warnings that were worked around, first-run hints, updates and the results of
+    /// actions from the menu.
+    Normal,
+    /// Also the number of logs archived by each scan.
+    Verbose,
+}
+
+/// Which timestamp of a logfile decides the partition it is filed under.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PartitionDate {
+    /// The first timestamp in the log header.
+    #[default]
+    Header,
+    /// The creation time of the logfile.
+    Created,
+}
+
+#[derive(Debug, Default, Clone, Copy)]
+struct DiskUsage {
+    /// The sum of all file sizes, as Explorer would report it.
+    logical: u64,
+    /// Counts files sharing storage through hard links once.
+    unique: u64,
+}
+
+/// The archived logs of one month.
+struct MonthStats {
+    /// The first day of the month.
+    month: NaiveDate,
+    logs: usize,
+    size: u64,
+    /// The earliest log, by day and then by name.
+    first_log: (NaiveDate, PathBuf),
+}
+
+struct ArchiveStats {
+    /// Only months with logs, oldest first.
+    months: Vec<MonthStats>,
+}
+
+#[derive(Clone)]
+struct UnrotateCollection {
+    collection_path: PathBuf,
+    link_mode: LinkMode,
+    // only report what would be linked, without touching the collection
+    dry_run: bool,
+    // set when duplicate content should be skipped
+    content_index: Option<RefCell<ContentIndex>>,
+    // set when `archive_index` is on
+    archive_index: Option<ArchiveIndex>,
+    partition_template: PartitionTemplate,
+    // links each archived log into `FLAT_FOLDER` as well
+    flat_mirror: bool,
+    fs: Arc<dyn FileSystem + Send + Sync>,
+}
+
+impl UnrotateCollection {
+    fn new(collection_path: PathBuf, link_mode: LinkMode) -> Self {
+        Self {
+            collection_path,
+            link_mode,
+            dry_run: false,
+            content_index: None,
+            archive_index: None,
+            partition_template: PartitionTemplate::default(),
+            flat_mirror: false,
+            fs: Arc::new(OsFileSystem),
+        }
+    }
+
+    fn enable_dedupe(&mut self) {
+        let content_index = ContentIndex::new(self.collection_path.clone());
+        self.content_index = Some(RefCell::new(content_index));
+    }
+
+    fn enable_archive_index(&mut self) {
+        self.archive_index = Some(ArchiveIndex::new(self.collection_path.clone()));
+    }
+
+    /// Builds the archive index from the manifests of the partitions if it is missing or
+    /// damaged.
+    fn check_archive_index(&self) -> io::Result<()> {
+        match self.archive_index {
+            Some(ref archive_index) if archive_index.needs_rebuild() => {
+                self.rebuild_archive_index()
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Writes the archive index anew from the manifests of the partitions.
+    fn rebuild_archive_index(&self) -> io::Result<()> {
+        let archive_index = match self.archive_index {
+            Some(ref archive_index) => archive_index,
+            None => return Ok(()),
+        };
+        let mut entries = vec![];
+        for (date, partition_folder_path) in self.partition_folders()? {
+            for entry in Manifest::load(&*self.fs, &partition_folder_path)?.entries {
+                let mut archived_path = partition_folder_path.join(&entry.file_name);
+                if !self.fs.exists(&archived_path) {
+                    archived_path = append_extension(&archived_path, "gz");
+                    if !self.fs.exists(&archived_path) {
+                        continue;
+                    }
+                }
+                entries.push(IndexEntry {
+                    date,
+                    first_timestamp: entry.first_timestamp,
+                    original_path: entry.original_path,
+                    size: entry.size,
+                    archived_path: archived_path
+                        .strip_prefix(&self.collection_path)
+                        .unwrap()
+                        .to_owned(),
+                });
+            }
+        }
+        archive_index.rebuild(&entries)
+    }
+
+    fn with_locallow_path(locallow_path: &Path, link_mode: LinkMode) -> Self {
+        let collection_path = app_data_path(locallow_path).join("Logs");
+        Self::new(collection_path, link_mode)
+    }
+
+    fn partition_folder_path(&self, date: NaiveDate) -> PathBuf {
+        self.collection_path
+            .join(self.partition_template.render(date))
+    }
+
+    /// Copies `artifact`, e.g. a crash dump, into `folder_name` within the partition for
+    /// `date` as `file_name`. Returns whether it was new to the collection.
+    fn archive_artifact(
+        &self,
+        artifact: &Path,
+        date: NaiveDate,
+        folder_name: &str,
+        file_name: &OsStr,
+    ) -> io::Result<bool> {
+        let folder_path = self.partition_folder_path(date).join(folder_name);
+        let archived_path = folder_path.join(file_name);
+        if self.dry_run {
+            return Ok(!self.fs.exists(&archived_path));
+        }
+        self.fs.create_dir_all(&folder_path)?;
+        copy_if_changed(&*self.fs, artifact, &archived_path)
+    }
+
+    /// Lists the existing partition folders along with the dates they represent.
+    fn partition_folders(&self) -> io::Result<Vec<(NaiveDate, PathBuf)>> {
+        self.partition_folders_in(&self.partition_template)
+    }
+
+    /// Like `partition_folders`, but for the layout of `template`.
+    fn partition_folders_in(
+        &self,
+        template: &PartitionTemplate,
+    ) -> io::Result<Vec<(NaiveDate, PathBuf)>> {
+        // collects the folders exactly `depth` levels below `folder`, as `/`-separated paths
+        fn find_folders(
+            fs: &dyn FileSystem,
+            folder: &Path,
+            relative_path: &str,
+            depth: usize,
+            found: &mut Vec<(String, PathBuf)>,
+        ) -> io::Result<()> {
+            for (path, is_dir) in fs.read_dir(folder)? {
+                if !is_dir {
+                    continue;
+                }
+                let name = match path.file_name().unwrap().to_os_string().into_string() {
+                    Ok(name) => name,
+                    Err(_) => continue,
+                };
+                let relative_path = if relative_path.is_empty() {
+                    name
+                } else {
+                    format!("{}/{}", relative_path, name)
+                };
+                if depth == 1 {
+                    found.push((relative_path, path));
+                } else {
+                    find_folders(fs, &path, &relative_path, depth - 1, found)?;
+                }
+            }
+            Ok(())
+        }
+        let mut found = vec![];
+        let depth = template.depth();
+        match find_folders(&*self.fs, &self.collection_path, "", depth, &mut found) {
+            Ok(()) => {}
+            Err(e)
+                if e.kind() == io::ErrorKind::NotFound
+                    && !self.fs.exists(&self.collection_path) => {}
+            Err(e) => return Err(e),
+        }
+        Ok(found
+            .into_iter()
+            .filter_map(|(relative_path, path)| {
+                let date = template.parse(&relative_path)?;
+                Some((date, path))
+            })
+            .collect())
+    }
+
+    /// Lists every file in the partitions along with the partition folder it is in.
+    fn archived_logfile_paths(&self) -> io::Result<Vec<(PathBuf, PathBuf)>> {
+        let mut paths = vec![];
+        for (_, partition_folder_path) in self.partition_folders()? {
+            for path in partition_files(&*self.fs, &partition_folder_path)? {
+                paths.push((partition_folder_path.clone(), path));
+            }
+        }
+        Ok(paths)
+    }
+
+    /// The latest `count` partitions that hold any file, newest first.
+    fn recent_partitions(&self, count: usize) -> io::Result<Vec<(NaiveDate, PathBuf)>> {
+        let mut partitions = self.partition_folders()?;
+        partitions.sort_by_key(|&(date, _)| std::cmp::Reverse(date));
+        let mut recent = vec![];
+        for (date, partition_folder_path) in partitions {
+            if recent.len() == count {
+                break;
+            }
+            if !partition_files(&*self.fs, &partition_folder_path)?.is_empty() {
+                recent.push((date, partition_folder_path));
+            }
+        }
+        Ok(recent)
+    }
+
+    /// Counts the archived logs of each month, leaving out the `.meta` sidecars and
+    /// crash dumps.
+    fn archive_stats(&self) -> io::Result<ArchiveStats> {
+        let mut months = BTreeMap::new();
+        for (date, partition_folder_path) in self.partition_folders()? {
+            let month = date.with_day(1).unwrap();
+            for path in partition_files(&*self.fs, &partition_folder_path)? {
+                if is_artifact(&path) || path.extension() == Some(OsStr::new("meta")) {
+                    continue;
+                }
+                let metadata = fs::metadata(&path)?;
+                let stats = months.entry(month).or_insert_with(|| MonthStats {
+                    month,
+                    logs: 0,
+                    size: 0,
+                    first_log: (date, path.clone()),
+                });
+                stats.logs += 1;
+                stats.size += metadata.len();
+                if (date, &path) < (stats.first_log.0, &stats.first_log.1) {
+                    stats.first_log = (date, path);
+                }
+            }
+        }
+        Ok(ArchiveStats {
+            months: months.into_values().collect(),
+        })
+    }
+
+    /// Deletes every partition older than `cutoff`, along with parent folders (e.g. months)
+    /// left empty.
+    fn remove_partitions_before(&self, cutoff: NaiveDate) -> io::Result<()> {
+        for (date, partition_folder_path) in self.partition_folders()? {
+            if date >= cutoff {
+                continue;
+            }
+            fs::remove_dir_all(&partition_folder_path)?;
+            let mut parent_path = partition_folder_path.parent().unwrap();
+            while parent_path != self.collection_path && parent_path.read_dir()?.next().is_none() {
+                fs::remove_dir(parent_path)?;
+                parent_path = parent_path.parent().unwrap();
+            }
+        }
+        if let Some(ref archive_index) = self.archive_index {
+            archive_index.remove_before(cutoff)?;
+        }
+        // the mirror would otherwise keep the removed logs on disk
+        self.remove_flat_before(cutoff)
+    }
+
+    /// Removes the folders below the collection that hold no files, at any depth, except
+    /// those modified within `min_age`, which may have just been created for a link.
+    /// Returns the number of folders removed.
+    fn remove_empty_folders(&self, min_age: Duration) -> io::Result<usize> {
+        fn prune(folder_path: &Path, min_age: Duration) -> io::Result<(bool, usize)> {
+            let mut empty = true;
+            let mut removed = 0;
+            for entry in folder_path.read_dir()? {
+                let entry = entry?;
+                // a symlink to a folder counts as a file, so nothing outside is touched
+                if !entry.file_type()?.is_dir() {
+                    empty = false;
+                    continue;
+                }
+                let path = entry.path();
+                // before pruning within it, which makes it look modified just now
+                let recent = SystemTime::now()
+                    .duration_since(entry.metadata()?.modified()?)
+                    .map_or(true, |age| age < min_age);
+                let (child_empty, child_removed) = prune(&path, min_age)?;
+                removed += child_removed;
+                if !child_empty || recent {
+                    empty = false;
+                    continue;
+                }
+                match fs::remove_dir(&path) {
+                    Ok(()) => removed += 1,
+                    // something was put there meanwhile
+                    Err(_)
+                        if path
+                            .read_dir()
+                            .is_ok_and(|mut entries| entries.next().is_some()) =>
+                    {
+                        empty = false
+                    }
+                    Err(e) => return Err(e),
+                }
+            }
+            Ok((empty, removed))
+        }
+        prune(&self.collection_path, min_age).map(|(_, removed)| removed)
+    }
+
+    /// How many partition folders there are in each built-in layout other than the one
+    /// in use, as left behind when `partition_template` is changed. Those are not moved.
+    fn other_layouts(&self) -> io::Result<Vec<(PartitionTemplate, usize)>> {
+        let mut others = vec![];
+        for template in [partition::DEFAULT_TEMPLATE, partition::YEARLY_TEMPLATE] {
+            let template = PartitionTemplate::new(template).unwrap();
+            if template.as_str() == self.partition_template.as_str() {
+                continue;
+            }
+            let count = self.partition_folders_in(&template)?.len();
+            if count > 0 {
+                others.push((template, count));
+            }
+        }
+        Ok(others)
+    }
+
+    /// Moves everything in the partition folders of `other_layouts` into the partition for
+    /// the same date in the layout in use, keeping its place within the partition, then
+    /// removes the folders left empty. Stopping halfway is fine, as running it again picks
+    /// up the files still to move. Refuses to move anything if an archived log's header
+    /// puts it more than a day away from its folder, as the folder may not be a partition.
+    fn reorganize(&self) -> Result<ReorganizeStats> {
+        let mut moves = vec![];
+        let mut partitions = vec![];
+        for (template, _) in self.other_layouts()? {
+            for (date, old_partition_path) in self.partition_folders_in(&template)? {
+                let new_partition_path = self.partition_folder_path(date);
+                for path in partition_files(&*self.fs, &old_partition_path)? {
+                    // merged into the manifest already there below
+                    if path == Manifest::path(&old_partition_path) {
+                        continue;
+                    }
+                    if !is_artifact(&path) && path.extension() == Some("txt".as_ref()) {
+                        let logfile = match VRCLogfile::new(&*self.fs, path.clone(), false) {
+                            // moved along with the others, like a log without a header
+                            Err(e) if e.kind() == io::ErrorKind::InvalidData => None,
+                            logfile => logfile
+                                .with_context(|| format!("failed to read {}", path.display()))?,
+                        };
+                        // a day either way, which `partition_date` and `utc_offset_minutes`
+                        // may account for
+                        if let Some(logfile) = logfile {
+                            if (logfile.timestamp.date() - date).num_days().abs() > 1 {
+                                anyhow::bail!(
+                                    "{} starts on {}, which does not fit its folder; nothing \
+                                     has been moved",
+                                    path.display(),
+                                    logfile.timestamp.date()
+                                );
+                            }
+                        }
+                    }
+                    let relative_path = path.strip_prefix(&old_partition_path).unwrap();
+                    let new_path = new_partition_path.join(relative_path);
+                    moves.push((path, new_path));
+                }
+                partitions.push((old_partition_path, new_partition_path));
+            }
+        }
+        // the sidecars go last, so that they follow what became of their logs
+        moves.sort_by_key(|(path, _)| path.extension() == Some("meta".as_ref()));
+        let mut stats = ReorganizeStats::default();
+        for (path, new_path) in moves {
+            let move_context = || format!("failed to move {}", path.display());
+            let is_meta = path.extension() == Some("meta".as_ref());
+            if is_meta && stats.conflicts.contains(&path.with_extension("")) {
+                stats.conflicts.push(path);
+                continue;
+            }
+            if new_path.exists() {
+                // moved before, but the old one was not removed yet. a sidecar may differ,
+                // e.g. in its date, and the one next to the log there is kept
+                if is_meta
+                    || content_index::hash_file(&path).with_context(move_context)?
+                        == content_index::hash_file(&new_path).with_context(move_context)?
+                {
+                    fs::remove_file(&path).with_context(move_context)?;
+                    stats.moved += 1;
+                } else {
+                    stats.conflicts.push(path);
+                }
+                continue;
+            }
+            fs::create_dir_all(new_path.parent().unwrap()).with_context(move_context)?;
+            fs::rename(&path, &new_path).with_context(move_context)?;
+            if let Some(ref content_index) = self.content_index {
+                content_index
+                    .borrow_mut()
+                    .rename(&path, &new_path)
+                    .context("failed to update the content index")?;
+            }
+            stats.moved += 1;
+        }
+        for (old_partition_path, new_partition_path) in partitions {
+            self.merge_manifest(&old_partition_path, &new_partition_path, &stats.conflicts)
+                .with_context(|| {
+                    format!(
+                        "failed to move the manifest of {}",
+                        old_partition_path.display()
+                    )
+                })?;
+        }
+        self.remove_empty_folders(Duration::ZERO)
+            .context("failed to remove the emptied folders")?;
+        // nearly every path in it has changed
+        self.rebuild_archive_index()
+            .context("failed to rebuild the archive index")?;
+        Ok(stats)
+    }
+
+    /// Adds the entries of the manifest in `old_partition_path` to the one in
+    /// `new_partition_path`, where their files were moved, and removes the old manifest.
+    /// The entries for `conflicts` stay behind along with their files.
+    fn merge_manifest(
+        &self,
+        old_partition_path: &Path,
+        new_partition_path: &Path,
+        conflicts: &[PathBuf],
+    ) -> io::Result<()> {
+        let old_manifest_path = Manifest::path(old_partition_path);
+        if !self.fs.exists(&old_manifest_path) {
+            return Ok(());
+        }
+        let old_manifest = Manifest::load(&*self.fs, old_partition_path)?;
+        let mut manifest = Manifest::load(&*self.fs, new_partition_path)?;
+        let mut left = Manifest::default();
+        for entry in old_manifest.entries {
+            let conflicted = conflicts.iter().any(|path| {
+                path.starts_with(old_partition_path)
+                    && path.file_name() == Some(entry.file_name.as_ref())
+            });
+            if conflicted {
+                left.entries.push(entry);
+            } else {
+                manifest.upsert(entry);
+            }
+        }
+        self.fs.create_dir_all(new_partition_path)?;
+        manifest.save(&*self.fs, new_partition_path)?;
+        if left.entries.is_empty() {
+            self.fs.remove_file(&old_manifest_path)
+        } else {
+            left.save(&*self.fs, old_partition_path)
+        }
+    }
+
+    /// Concatenates each run of logs in the partition for `date` that follow one another
+    /// within `gap`, as when VRChat restarted mid-session, into `MERGED_FOLDER` under the
+    /// name of the first. The logs themselves stay as they are, and logs that overlap in
+    /// time, i.e. from VRChat instances running side by side, are never merged.
+    /// Returns the number of merged logs written.
+    fn merge_split_logs(&self, date: NaiveDate, gap: chrono::Duration) -> io::Result<usize> {
+        let partition_folder_path = self.partition_folder_path(date);
+        let mut logs = vec![];
+        for path in partition_files(&*self.fs, &partition_folder_path)? {
+            if is_artifact(&path) || path.extension() != Some("txt".as_ref()) {
+                continue;
+            }
+            let logfile = match VRCLogfile::new(&*self.fs, path.clone(), false) {
+                Ok(logfile) => logfile,
+                // left as it is, like a log without a header
+                Err(e) if e.kind() == io::ErrorKind::InvalidData => None,
+                Err(e) => return Err(e),
+            };
+            if let Some(logfile) = logfile {
+                // the last write is as close to the end of the session as there is
+                let modified: DateTime<Local> = fs::metadata(&path)?.modified()?.into();
+                logs.push((logfile.timestamp, modified.naive_local(), path));
+            }
+        }
+        logs.sort();
+        let mut runs: Vec<Vec<PathBuf>> = vec![];
+        let mut run_end = None;
+        for (started, ended, path) in logs {
+            match (runs.last_mut(), run_end) {
+                (Some(run), Some(run_end)) if started >= run_end && started - run_end <= gap => {
+                    run.push(path)
+                }
+                _ => runs.push(vec![path]),
+            }
+            run_end = Some(ended);
+        }
+        let merged_folder_path = partition_folder_path.join(MERGED_FOLDER);
+        let mut merged = 0;
+        for run in runs.iter().filter(|run| run.len() > 1) {
+            let merged_path = merged_folder_path.join(run[0].file_name().unwrap());
+            if merged_path.exists() || append_extension(&merged_path, "gz").exists() {
+                continue;
+            }
+            fs::create_dir_all(&merged_folder_path)?;
+            // only a complete merge ever gets the final name
+            let tmp_path = append_extension(&merged_path, "tmp");
+            let mut writer = io::BufWriter::new(File::create(&tmp_path)?);
+            for path in run {
+                io::copy(&mut VRCLogfile::open(path)?, &mut writer)?;
+            }
+            writer.into_inner()?.sync_all()?;
+            fs::rename(&tmp_path, &merged_path)?;
+            merged += 1;
+        }
+        Ok(merged)
+    }
+
+    /// Gzips every plain logfile in the partition and removes the originals.
+    /// Interrupted runs are picked up again on the next call.
+    fn compress_partition(&self, date: NaiveDate) -> io::Result<()> {
+        let mut renames = vec![];
+        for path in partition_files(&*self.fs, &self.partition_folder_path(date))? {
+            if path.extension() != Some("txt".as_ref()) {
+                continue;
+            }
+            let gz_path = append_extension(&path, "gz");
+            if !gz_path.exists() {
+                // only a complete archive ever gets the final name
+                let tmp_path = append_extension(&path, "gz.tmp");
+                let mut encoder = GzEncoder::new(File::create(&tmp_path)?, Compression::default());
+                io::copy(&mut File::open(&path)?, &mut encoder)?;
+                encoder.finish()?.sync_all()?;
+                fs::rename(&tmp_path, &gz_path)?;
+            }
+            fs::remove_file(&path)?;
+            // the flat link would keep the uncompressed log on the drive
+            if self.flat_mirror && !is_artifact(&path) {
+                match fs::remove_file(self.flat_path(&path, date)) {
+                    Err(e) if e.kind() != io::ErrorKind::NotFound => return Err(e),
+                    _ => {}
+                }
+                self.link_flat(&gz_path, date)?;
+            }
+            renames.push((path, gz_path));
+        }
+        match self.archive_index {
+            Some(ref archive_index) => archive_index.rename(&renames),
+            None => Ok(()),
+        }
+    }
+
+    /// Zips everything under `folder`, which is either the collection itself or a folder in it,
+    /// with entry names relative to the collection. Returns the number of files written.
+    fn export_zip(&self, folder: &Path, zip_path: &Path) -> io::Result<usize> {
+        fn add_folder<W: io::Write>(
+            zip: &mut ZipWriter<W>,
+            collection_path: &Path,
+            folder: &Path,
+            zip_path: &Path,
+        ) -> io::Result<usize> {
+            let mut count = 0;
+            for entry in folder.read_dir()? {
+                let entry = entry?;
+                let path = entry.path();
+                if entry.file_type()?.is_dir() {
+                    // the same logs once more, under other names
+                    if path != collection_path.join(FLAT_FOLDER) {
+                        count += add_folder(zip, collection_path, &path, zip_path)?;
+                    }
+                    continue;
+                }
+                // the archive may be saved into the very folder being exported
+                if path == zip_path {
+                    continue;
+                }
+                let name = path
+                    .strip_prefix(collection_path)
+                    .unwrap_or(&path)
+                    .to_string_lossy()
+                    .replace('\\', "/");
+                let modified: DateTime<Local> = entry.metadata()?.modified()?.into();
+                let mut file = VRCLogfile::open(&path)?;
+                zip.add_file(&name, modified.naive_local(), &mut file)?;
+                count += 1;
+            }
+            Ok(count)
+        }
+        let result = File::create(zip_path).and_then(|file| {
+            let mut zip = ZipWriter::new(io::BufWriter::new(file));
+            let count = add_folder(&mut zip, &self.collection_path, folder, zip_path)?;
+            zip.finish()?;
+            Ok(count)
+        });
+        if result.is_err() {
+            // a truncated archive is worse than none
+            let _ = fs::remove_file(zip_path);
+        }
+        result
+    }
+
+    /// Sums the sizes of all files in the collection, both as listed and with each
+    /// hard-linked file counted only once.
+    fn disk_usage(&self) -> io::Result<DiskUsage> {
+        fn add_folder(
+            usage: &mut DiskUsage,
+            seen: &mut HashSet<(u32, u64)>,
+            folder: &Path,
+        ) -> io::Result<()> {
+            for entry in folder.read_dir()? {
+                let entry = entry?;
+                if entry.file_type()?.is_dir() {
+                    add_folder(usage, seen, &entry.path())?;
+                    continue;
+                }
+                let file = VRCLogfile::open(&entry.path())?;
+                let mut info: BY_HANDLE_FILE_INFORMATION = unsafe { mem::zeroed() };
+                if unsafe { GetFileInformationByHandle(file.as_raw_handle(), &mut info) } == 0 {
+                    return Err(io::Error::last_os_error());
+                }
+                let size = (u64::from(info.nFileSizeHigh) << 32) | u64::from(info.nFileSizeLow);
+                let file_index =
+                    (u64::from(info.nFileIndexHigh) << 32) | u64::from(info.nFileIndexLow);
+                usage.logical += size;
+                if seen.insert((info.dwVolumeSerialNumber, file_index)) {
+                    usage.unique += size;
+                }
+            }
+            Ok(())
+        }
+        let mut usage = DiskUsage::default();
+        if self.collection_path.exists() {
+            add_folder(&mut usage, &mut HashSet::new(), &self.collection_path)?;
+        }
+        Ok(usage)
+    }
+
+    /// Picks the name `logfile` is archived under: `wanted_name`, unless a different log
+    /// already took that name in the partition, in which case a numbered one such as
+    /// `output_log_..._2.txt`. The name may include a world folder.
+    fn free_file_name(
+        &self,
+        logfile: &VRCLogfile,
+        date: NaiveDate,
+        wanted_name: &OsStr,
+    ) -> io::Result<OsString> {
+        let partition_folder_path = self.partition_folder_path(date);
+        let wanted_name = Path::new(wanted_name);
+        let stem = wanted_name.file_stem().unwrap_or(wanted_name.as_os_str());
+        for n in 1.. {
+            let file_name = if n == 1 {
+                wanted_name.as_os_str().to_owned()
+            } else {
+                let mut file_name = stem.to_owned();
+                file_name.push(format!("_{}", n));
+                if let Some(extension) = wanted_name.extension() {
+                    file_name.push(".");
+                    file_name.push(extension);
+                }
+                wanted_name.with_file_name(file_name).into_os_string()
+            };
+            if !is_other_log(&*self.fs, logfile, &partition_folder_path.join(&file_name))? {
+                return Ok(file_name);
+            }
+        }
+        unreachable!()
+    }
+
+    /// Returns `true` if the logfile was newly added to the collection.
+    /// `date` selects the partition, see `Unrotate::partition_date`, and `file_name` the name
+    /// within it, which is kept even if VRChat renames the source later.
+    fn create_link(
+        &self,
+        logfile: &VRCLogfile,
+        date: NaiveDate,
+        file_name: &OsStr,
+        keep: bool,
+    ) -> io::Result<bool> {
+        let partition_folder_path = self.partition_folder_path(date);
+        let new_link_path = partition_folder_path.join(file_name);
+        if self.fs.exists(&append_extension(&new_link_path, "gz")) {
+            return Ok(false);
+        }
+        if self.dry_run {
+            // reported by the caller, see `FileResult`
+            return Ok(!self.fs.exists(&new_link_path));
+        }
+        let content_hash = match self.content_index {
+            Some(ref content_index) if !self.fs.exists(&new_link_path) => {
+                let hash = content_index::hash_file(&logfile.path)?;
+                if content_index.borrow_mut().find(&hash)?.is_some() {
+                    return Ok(false);
+                }
+                Some(hash)
+            }
+            _ => None,
+        };
+        // the name may put the log in a world folder within the partition
+        self.fs.create_dir_all(new_link_path.parent().unwrap())?;
+        let is_new = self.place_logfile(&logfile.path, &new_link_path, keep)?;
+        if !self.fs.exists(&new_link_path) {
+            // still in use, see `move_if_closed`
+            return Ok(false);
+        }
+        let meta_path = append_extension(&new_link_path, "meta");
+        if is_new || !self.fs.exists(&meta_path) {
+            let meta = LogfileMeta {
+                date: logfile.date(),
+                original_path: &logfile.path,
+                version: logfile.version.as_deref(),
+            };
+            self.fs
+                .write(&meta_path, &serde_json::to_vec_pretty(&meta)?)?;
+        }
+        let mut manifest = Manifest::load(&*self.fs, &partition_folder_path)?;
+        let entry = ManifestEntry {
+            file_name: file_name.to_string_lossy().into_owned(),
+            original_path: logfile.path.clone(),
+            size: self.fs.metadata(&new_link_path)?.len,
+            first_timestamp: logfile.timestamp,
+            archived_at: Local::now(),
+        };
+        match manifest.upsert(entry.clone()) {
+            Upsert::Unchanged => {}
+            // a live log grows with every poll, so its entry is only brought up to date
+            // once the session is over, and the index's along with it
+            Upsert::Updated if self.fs.is_in_use(&logfile.path)? => {}
+            Upsert::Updated | Upsert::Added => {
+                manifest.save(&*self.fs, &partition_folder_path)?;
+                if let Some(ref archive_index) = self.archive_index {
+                    archive_index.insert(&IndexEntry {
+                        date,
+                        first_timestamp: entry.first_timestamp,
+                        original_path: entry.original_path,
+                        size: entry.size,
+                        archived_path: new_link_path.clone(),
+                    })?;
+                }
+            }
+        }
+        if let (Some(ref content_index), Some(hash)) = (&self.content_index, content_hash) {
+            content_index.borrow_mut().insert(hash, &new_link_path)?;
+        }
+        if self.flat_mirror {
+            self.link_flat(&new_link_path, date)?;
+        }
+        Ok(is_new)
+    }
+
+    /// Hard links `archived_path` into `FLAT_FOLDER`, prefixed with `date`, unless it
+    /// is there already.
+    fn link_flat(&self, archived_path: &Path, date: NaiveDate) -> io::Result<()> {
+        let flat_path = self.flat_path(archived_path, date);
+        // a link to the same file reports the same size; anything else is stale
+        match self.fs.metadata(&flat_path) {
+            Ok(metadata) if metadata.len == self.fs.metadata(archived_path)?.len => {
+                return Ok(());
+            }
+            Ok(_) => self.fs.remove_file(&flat_path)?,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+            Err(e) => return Err(e),
+        }
+        self.fs.create_dir_all(flat_path.parent().unwrap())?;
+        match self.fs.hard_link(archived_path, &flat_path) {
+            Err(e) if e.kind() != io::ErrorKind::AlreadyExists => Err(e),
+            _ => Ok(()),
+        }
+    }
+
+    /// Where `link_flat` links `archived_path` to.
+    fn flat_path(&self, archived_path: &Path, date: NaiveDate) -> PathBuf {
+        let mut flat_name = OsString::from(date.format("%Y-%m-%d_").to_string());
+        // without any world folder, which would only make the folder less flat
+        flat_name.push(archived_path.file_name().unwrap());
+        self.collection_path.join(FLAT_FOLDER).join(flat_name)
+    }
+
+    /// Deletes the links in `FLAT_FOLDER` to logs from before `cutoff`.
+    fn remove_flat_before(&self, cutoff: NaiveDate) -> io::Result<()> {
+        let flat_folder_path = self.collection_path.join(FLAT_FOLDER);
+        let entries = match flat_folder_path.read_dir() {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(()),
+            Err(e) => return Err(e),
+        };
+        for entry in entries {
+            let entry = entry?;
+            let file_name = entry.file_name();
+            let date = file_name
+                .to_str()
+                .and_then(|file_name| file_name.get(..10))
+                .and_then(|date| NaiveDate::parse_from_str(date, "%Y-%m-%d").ok());
+            if date.is_some_and(|date| date < cutoff) {
+                fs::remove_file(entry.path())?;
+            }
+        }
+        Ok(())
+    }
+
+    /// With `keep`, the source stays where it is even in Move mode.
+    fn place_logfile(
+        &self,
+        source_path: &Path,
+        new_link_path: &Path,
+        keep: bool,
+    ) -> io::Result<bool> {
+        let fs = &*self.fs;
+        match self.link_mode {
+            LinkMode::Copy => return copy_if_changed(fs, source_path, new_link_path),
+            LinkMode::Move if keep => return copy_if_changed(fs, source_path, new_link_path),
+            LinkMode::Move => return move_if_closed(fs, source_path, new_link_path),
+            _ => {}
+        }
+        // a proper link always reports the same size as its source
+        let existed = match fs.metadata(new_link_path) {
+            Ok(metadata) if metadata.len == fs.metadata(source_path)?.len => {
+                return Ok(false);
+            }
+            Ok(_) => {
+                // e.g. truncated by an earlier crash, or a stale fallback copy
+                fs.remove_file(new_link_path)?;
+                true
+            }
+            Err(e) => match e.kind() {
+                io::ErrorKind::NotFound => false,
+                _ => return Err(e),
+            },
+        };
+        let result = match self.link_mode {
+            LinkMode::HardLink => fs.hard_link(source_path, new_link_path),
+            LinkMode::Symlink => fs.symlink_file(source_path, new_link_path),
+            LinkMode::Copy | LinkMode::Move => unreachable!(),
+        };
+        match result {
+            Ok(_) => Ok(!existed),
+            Err(e) => match e.kind() {
+                io::ErrorKind::AlreadyExists => Ok(false),
+                // hard links cannot span volumes, e.g. when the collection lives on another drive
+                _ if e.raw_os_error() == Some(ERROR_NOT_SAME_DEVICE as i32) => {
+                    copy_if_changed(fs, source_path, new_link_path).map(|is_new| is_new && !existed)
+                }
+                _ => Err(e),
+            },
+        }
+    }
+}
+
+/// Lists the files in a partition, including those in world folders.
+fn partition_files(fs: &dyn FileSystem, partition_folder_path: &Path) -> io::Result<Vec<PathBuf>> {
+    let mut paths = vec![];
+    for (path, is_dir) in fs.read_dir(partition_folder_path)? {
+        if is_dir {
+            paths.extend(fs.list_files(&path)?);
+        } else {
+            paths.push(path);
+        }
+    }
+    Ok(paths)
+}
+
+/// Whether `path` is something kept in a partition besides the archived logs themselves,
+/// e.g. a crash dump or a merged log.
+fn is_artifact(path: &Path) -> bool {
+    matches!(
+        path.parent()
+            .and_then(Path::file_name)
+            .and_then(OsStr::to_str),
+        Some(CRASH_DUMPS_FOLDER) | Some(MERGED_FOLDER)
+    )
+}
+
+/// Turns a world name into a folder name Windows accepts, or `None` if nothing is left.
+fn world_folder_name(world: &str) -> Option<String> {
+    const MAX_LEN: usize = 64;
+    let name: String = world
+        .chars()
+        .map(|c| {
+            if "<>:\"/\\|?*".contains(c) || c.is_control() {
+                '_'
+            } else {
+                c
+            }
+        })
+        .take(MAX_LEN)
+        .collect();
+    // trailing dots and spaces are dropped by Windows, which would merge folders
+    let name = name.trim().trim_end_matches('.').trim_end();
+    if name.is_empty() {
+        return None;
+    }
+    let stem = name.split('.').next().unwrap().to_ascii_uppercase();
+    let reserved = matches!(stem.as_str(), "CON" | "PRN" | "AUX" | "NUL")
+        || (stem.len() == 4
+            && (stem.starts_with("COM") || stem.starts_with("LPT"))
+            && stem.as_bytes()[3].is_ascii_digit());
+    Some(if reserved {
+        format!("_{}", name)
+    } else {
+        name.to_string()
+    })
+}
+
+/// Whether `archived_path` already holds a log other than `logfile`. A file whose header
+/// cannot be told apart, e.g. one truncated by a crash, counts as the same log.
+fn is_other_log(
+    fs: &dyn FileSystem,
+    logfile: &VRCLogfile,
+    archived_path: &Path,
+) -> io::Result<bool> {
+    match VRCLogfile::new(fs, archived_path.to_owned(), false) {
+        Ok(Some(archived)) => return Ok(archived.identity() != logfile.identity()),
+        Ok(None) => return Ok(false),
+        // as with a missing header, there is nothing to tell it apart by
+        Err(e)
+            if matches!(
+                e.kind(),
+                io::ErrorKind::PermissionDenied | io::ErrorKind::InvalidData
+            ) =>
+        {
+            return Ok(false)
+        }
+        Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+        Err(e) => return Err(e),
+    }
+    // once compressed, only the sidecar tells where the log came from
+    let meta = match fs.read(&append_extension(archived_path, "meta")) {
+        Ok(meta) => meta,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(false),
+        Err(e) => return Err(e),
+    };
+    let meta: serde_json::Value = serde_json::from_slice(&meta)?;
+    Ok(match meta["original_path"].as_str() {
+        Some(original_path) => Path::new(original_path) != logfile.path,
+        None => false,
+    })
+}
+
+fn append_extension(path: &Path, extension: &str) -> PathBuf {
+    let mut path = path.as_os_str().to_owned();
+    path.push(".");
+    path.push(extension);
+    PathBuf::from(path)
+}
+
+/// The `count` most recently written of `paths`.
+fn newest_paths(fs: &dyn FileSystem, paths: &[PathBuf], count: usize) -> Vec<PathBuf> {
+    if count == 0 {
+        return vec![];
+    }
+    // going by the names would put legacy `output_log_HH-MM-SS.txt` logs out of order
+    let mut modified: Vec<_> = paths
+        .iter()
+        .filter_map(|path| {
+            let modified = fs.metadata(path).ok()?.modified;
+            Some((modified, path))
+        })
+        .collect();
+    modified.sort_unstable_by_key(|&(modified, _)| std::cmp::Reverse(modified));
+    modified
+        .into_iter()
+        .take(count)
+        .map(|(_, path)| path.clone())
+        .collect()
+}
+
+/// Returns `true` if `to` did not exist before.
+fn copy_if_changed(fs: &dyn FileSystem, from: &Path, to: &Path) -> io::Result<bool> {
+    let from_len = fs.metadata(from)?.len;
+    let is_new = match fs.metadata(to) {
+        Ok(metadata) if metadata.len == from_len => return Ok(false),
+        Ok(_) => false,
+        Err(e) => match e.kind() {
+            io::ErrorKind::NotFound => true,
+            _ => return Err(e),
+        },
+    };
+    fs.copy(from, to)?;
+    Ok(is_new)
+}
+
+/// Copies `from` to `to`, then reads `to` back and compares its checksum with that of the
+/// bytes written, trying once more before giving up. The source is only read once, which
+/// also keeps a log that grows meanwhile from failing the comparison.
+fn copy_verified(from: &Path, to: &Path) -> io::Result<()> {
+    fn copy(from: &Path, to: &Path) -> io::Result<bool> {
+        let mut reader = VRCLogfile::open(from)?;
+        // the modified time is kept like `fs::copy` does, as `rebuild_state` compares it
+        // with the source. the creation time as well, so that copies sort by session
+        let metadata = reader.metadata()?;
+        let mut times = FileTimes::new().set_modified(metadata.modified()?);
+        if let Ok(created) = metadata.created() {
+            times = times.set_created(created);
+        }
+        let mut writer = File::create(to)?;
+        let mut hasher = crc32fast::Hasher::new();
+        let mut buf = vec![0; 64 * 1024];
+        loop {
+            let len = reader.read(&mut buf)?;
+            if len == 0 {
+                break;
+            }
+            hasher.update(&buf[..len]);
+            writer.write_all(&buf[..len])?;
+        }
+        writer.set_times(times)?;
+        writer.sync_all()?;
+        drop(writer);
+        let mut written = crc32fast::Hasher::new();
+        let mut reader = File::open(to)?;
+        loop {
+            let len = reader.read(&mut buf)?;
+            if len == 0 {
+                break;
+            }
+            written.update(&buf[..len]);
+        }
+        Ok(written.finalize() == hasher.finalize())
+    }
+    if copy(from, to)? || copy(from, to)? {
+        return Ok(());
+    }
+    Err(io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!(
+            "the copy at {} does not match {}, is the drive failing?",
+            to.display(),
+            from.display()
+        ),
+    ))
+}
+
+/// Whether the session of the log at `path` is still going.
+fn is_in_use(path: &Path) -> io::Result<bool> {
+    // VRChat keeps the active log open, so exclusive access means its session has ended
+    match OpenOptions::new().read(true).share_mode(0).open(path) {
+        Ok(_) => Ok(false),
+        Err(e) if e.raw_os_error() == Some(ERROR_SHARING_VIOLATION as i32) => Ok(true),
+        Err(e) => Err(e),
+    }
+}
+
+/// Moves `from` to `to` unless VRChat still has it open, in which case nothing happens.
+/// Returns `true` if `to` did not exist before.
+fn move_if_closed(fs: &dyn FileSystem, from: &Path, to: &Path) -> io::Result<bool> {
+    if fs.is_in_use(from)? {
+        return Ok(false);
+    }
+    let is_new = !fs.exists(to);
+    match fs.rename(from, to) {
+        Ok(()) => Ok(is_new),
+        // the collection lives on another drive
+        Err(e) if e.raw_os_error() == Some(ERROR_NOT_SAME_DEVICE as i32) => {
+            // the source is only removed once its copy is known to be good
+            fs.copy(from, to)?;
+            fs.remove_file(from)?;
+            Ok(is_new)
+        }
+        Err(e) => Err(e),
+    }
+}
+
+/// The bytes available to the user on the volume `path` is on. `path` need not exist yet,
+/// e.g. a collection that nothing has been archived into.
+fn free_space(path: &Path) -> io::Result<u64> {
+    use std::os::windows::ffi::OsStrExt;
+    use std::{iter, mem, ptr};
+    use winapi::um::{fileapi::GetDiskFreeSpaceExW, winnt::ULARGE_INTEGER};
+
+    let existing = path.ancestors().find(|path| path.exists()).unwrap_or(path);
+    let path: Vec<_> = existing
+        .as_os_str()
+        .encode_wide()
+        .chain(iter::once(0))
+        .collect();
+    unsafe {
+        let mut available: ULARGE_INTEGER = mem::zeroed();
+        if GetDiskFreeSpaceExW(
+            path.as_ptr(),
+            &mut available,
+            ptr::null_mut(),
+            ptr::null_mut(),
+        ) == 0
+        {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(*available.QuadPart())
+    }
+}
+
+fn is_disk_full(e: &io::Error) -> bool {
+    matches!(
+        e.raw_os_error(),
+        Some(code) if code == ERROR_DISK_FULL as i32 || code == ERROR_HANDLE_DISK_FULL as i32
+    )
+}
+
+/// Like `fs::create_dir_all`, but turns on NTFS compression for each folder it creates.
+fn create_compressed_dir_all(path: &Path) -> io::Result<()> {
+    if path.is_dir() {
+        return Ok(());
+    }
+    if let Some(parent) = path.parent() {
+        create_compressed_dir_all(parent)?;
+    }
+    match fs::create_dir(path) {
+        Ok(()) => {}
+        Err(e) if e.kind() == io::ErrorKind::AlreadyExists => return Ok(()),
+        Err(e) => return Err(e),
+    }
+    let folder = OpenOptions::new()
+        .access_mode(FILE_READ_DATA | FILE_WRITE_DATA)
+        .share_mode(FILE_SHARE_READ | FILE_SHARE_WRITE | FILE_SHARE_DELETE)
+        // needed to open a directory at all
+        .custom_flags(FILE_FLAG_BACKUP_SEMANTICS)
+        .open(path)?;
+    let mut format = COMPRESSION_FORMAT_DEFAULT;
+    let mut returned = 0;
+    let ok = unsafe {
+        DeviceIoControl(
+            folder.as_raw_handle() as _,
+            FSCTL_SET_COMPRESSION,
+            &mut format as *mut _ as _,
+            mem::size_of_val(&format) as _,
+            std::ptr::null_mut(),
+            0,
+            &mut returned,
+            std::ptr::null_mut(),
+        )
+    };
+    if ok == 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+#[derive(Debug, Default)]
+struct ImportStats {
+    imported: usize,
+    /// Already archived, expired, filtered out, still being written or not a log after all.
+    skipped: usize,
+    warnings: Vec<anyhow::Error>,
+}
+
+#[derive(Debug, Default)]
+struct ReorganizeStats {
+    moved: usize,
+    /// Left where they are, as a different file of the same name is in the way.
+    conflicts: Vec<PathBuf>,
+}
+
+#[derive(Debug, Default)]
+struct StepStats {
+    scanned: usize,
+    /// The number of logfiles newly added to the collection.
+    linked: usize,
+    /// What happened to each logfile scanned.
+    files: Vec<FileResult>,
+    /// Problems that were worked around, to be reported without failing the step.
+    warnings: Vec<anyhow::Error>,
+}
+
+#[derive(Debug, Serialize)]
+struct FileResult {
+    path: PathBuf,
+    outcome: FileOutcome,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    archived_path: Option<PathBuf>,
+}
+
+impl FileResult {
+    fn new(path: PathBuf, outcome: FileOutcome) -> Self {
+        Self {
+            path,
+            outcome,
+            archived_path: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+enum FileOutcome {
+    /// Not modified since it was last looked at.
+    Unchanged,
+    /// Written to more recently than `min_age_secs`.
+    Active,
+    /// Last written before startup, with `archive_existing` off.
+    PreExisting,
+    /// The header is not complete yet, or missing.
+    NoHeader,
+    Filtered,
+    Expired,
+    /// Newly added to the collection, or would be in a dry run.
+    Linked,
+    AlreadyArchived,
+    /// Left for later in Move mode, as VRChat still has it open.
+    InUse,
+    /// Copied rather than moved in Move mode, as one of the `keep_recent` newest logs.
+    Kept,
+    /// Could not be opened for reading, e.g. because of its ACL.
+    Unreadable,
+}
+
+/// What `--json` prints.
+#[derive(Serialize)]
+struct JsonSummary<'a> {
+    dry_run: bool,
+    scanned: usize,
+    linked: usize,
+    /// Scanned but not linked, for whatever reason.
+    skipped: usize,
+    files: &'a [FileResult],
+    errors: Vec<String>,
+}
+
+struct Unrotate {
+    vrchats: Vec<LocalLowVRChat>,
+    collection: UnrotateCollection,
+    // modified time of each logfile as of when it was last linked
+    processed: HashMap<PathBuf, SystemTime>,
+    // the name each logfile was archived under, so a renamed source is not archived twice
+    archived_names: HashMap<LogIdentity, OsString>,
+    // sources known not to exist, so that they are reported only until they appear
+    missing_sources: HashSet<PathBuf>,
+    // whether the collection has been looked at for partitions in another layout
+    layout_checked: bool,
+    // logfiles that could not be opened, so that they are reported only once
+    unreadable: HashSet<PathBuf>,
+    // where `processed` and `archived_names` are kept across restarts
+    state_path: PathBuf,
+    last_scan: Option<DateTime<Local>>,
+    retention_days: Option<u32>,
+    compress_after_days: Option<u32>,
+    last_maintenance: Option<NaiveDate>,
+    partition_date_source: PartitionDate,
+    utc_offset: Option<FixedOffset>,
+    content_filter: Option<regex::bytes::Regex>,
+    // logs following one another within this are merged by `maintain`
+    merge_gap: Option<chrono::Duration>,
+    // how far into a log `content_filter` and `world_folders` look
+    scan_max_bytes: Option<u64>,
+    mmap_headers: bool,
+    // logs last written before this are left alone, unless `archive_existing` is on
+    started_at: Option<SystemTime>,
+    min_age: Duration,
+    keep_recent: usize,
+    // in bytes, 0 to never check
+    min_free_space: u64,
+    // whether the drive was below `min_free_space` when last checked, so that it is only
+    // told once, and that copying is paused meanwhile
+    low_space: bool,
+    // turned off for the session once the volume turns out not to support it
+    ntfs_compression: bool,
+    timestamp_names: bool,
+    world_folders: bool,
+    /// Source file names to leave alone.
+    exclude: RegexSet,
+    // excluded files already mentioned in the diagnostics
+    excluded: HashSet<PathBuf>,
+    notification_cooldown: Duration,
+    crash_dump_path: Option<PathBuf>,
+    // when each error was last notified, so that a chronic one is not notified on
+    // every restart
+    notified: HashMap<String, DateTime<Local>>,
+    diagnostics: Diagnostics,
+    // shared with `collection`
+    fs: Arc<dyn FileSystem + Send + Sync>,
+}
+
+impl Unrotate {
+    /// Calls `progress` before each logfile with how many of the logs not seen before have
+    /// been looked at so far, and how many there are in all. Stops early, though still
+    /// saving what was done, once it breaks.
+    fn step(
+        &mut self,
+        mut progress: impl FnMut(usize, usize) -> ControlFlow<()>,
+    ) -> Result<StepStats> {
+        let mut stats = StepStats::default();
+        if !self.layout_checked {
+            self.layout_checked = true;
+            match self.collection.other_layouts() {
+                Ok(others) => {
+                    for (template, count) in others {
+                        stats.warnings.push(anyhow::anyhow!(
+                            "{} partition folder(s) in the collection use the {} layout rather \
+                             than partition_template {}; they are left where they are, so the \
+                             collection mixes both layouts until moved with --reorganize",
+                            count,
+                            template.as_str(),
+                            self.collection.partition_template.as_str()
+                        ));
+                    }
+                }
+                Err(e) => self
+                    .diagnostics
+                    .log(format_args!("failed to look for other layouts: {}", e)),
+            }
+        }
+        // one unreadable source must not keep the others from being archived
+        let mut scan_error = None;
+        let mut paths = vec![];
+        let mut kept = HashSet::new();
+        for vrchat in &self.vrchats {
+            let (diagnostics, excluded) = (&self.diagnostics, &mut self.excluded);
+            let listed = vrchat.list_logfile_paths(&*self.fs, &self.exclude, |path| {
+                // only worth telling once per file
+                if !excluded.contains(&path) {
+                    diagnostics.log(format_args!("debug: excluded {}", path.display()));
+                    excluded.insert(path);
+                }
+            });
+            match listed {
+                Ok(vrchat_paths) => {
+                    self.missing_sources.remove(&vrchat.vrchat_path);
+                    if self.collection.link_mode == LinkMode::Move {
+                        kept.extend(newest_paths(&*self.fs, &vrchat_paths, self.keep_recent));
+                    }
+                    paths.extend(vrchat_paths);
+                }
+                Err(e)
+                    if e.downcast_ref::<io::Error>()
+                        .is_some_and(|e| e.kind() == io::ErrorKind::NotFound) =>
+                {
+                    // not an error worth backing off for, and only worth telling once.
+                    // where VRChat is installed makes no difference, as Unity always
+                    // writes the logs below LocalLow, so there is nowhere else to look
+                    if self.missing_sources.insert(vrchat.vrchat_path.clone()) {
+                        stats.warnings.push(anyhow::anyhow!(
+                            "VRChat log folder not found, is VRChat installed? Looked in {}; \
+                             set vrchat_log_dir if the logs are elsewhere",
+                            vrchat.vrchat_path.display()
+                        ));
+                    }
+                }
+                Err(e) => {
+                    let e = e.context(format!("failed to scan {}", vrchat.vrchat_path.display()));
+                    scan_error.get_or_insert(e);
+                }
+            }
+        }
+        stats.scanned = paths.len();
+        // copying onto a full drive would only fail the same way on every poll
+        let copying = matches!(self.collection.link_mode, LinkMode::Copy | LinkMode::Move);
+        // a full drive pauses copying even with the check turned off, until there is room
+        if self.min_free_space > 0 || self.low_space {
+            self.check_free_space(copying, &mut stats.warnings);
+        }
+        let paused = copying && self.low_space;
+        if paused {
+            paths.clear();
+        }
+        let mut changed = false;
+        let backlog = paths
+            .iter()
+            .filter(|&path| !self.processed.contains_key(path))
+            .count();
+        let mut done = 0;
+        let mut stopped = paused;
+        for path in paths {
+            // nothing is half done between two logfiles
+            if progress(done, backlog).is_break() {
+                stopped = true;
+                break;
+            }
+            if !self.processed.contains_key(&path) {
+                done += 1;
+            }
+            // the folder is in the settings, so the name is enough to tell which file it was
+            let file_name = path.file_name().unwrap_or_default().to_string_lossy();
+            let read_context = || format!("failed to read {}", file_name);
+            let modified = match self.fs.metadata(&path).map(|metadata| metadata.modified) {
+                Ok(modified) => modified,
+                // e.g. removed since it was listed, which is no reason to leave the rest
+                Err(e) => {
+                    if self.unreadable.insert(path.clone()) {
+                        stats
+                            .warnings
+                            .push(anyhow::Error::new(e).context(read_context()));
+                    }
+                    stats
+                        .files
+                        .push(FileResult::new(path, FileOutcome::Unreadable));
+                    continue;
+                }
+            };
+            if self.processed.get(&path) == Some(&modified) {
+                stats
+                    .files
+                    .push(FileResult::new(path, FileOutcome::Unchanged));
+                continue;
+            }
+            // not recorded as processed, so that turning `archive_existing` on picks it up
+            if self
+                .started_at
+                .is_some_and(|started_at| modified < started_at)
+            {
+                stats
+                    .files
+                    .push(FileResult::new(path, FileOutcome::PreExisting));
+                continue;
+            }
+            let (logfile, date) = match self.screen(&path, modified) {
+                Ok(screened) => {
+                    self.unreadable.remove(&path);
+                    match screened {
+                        Ok(screened) => screened,
+                        Err(outcome) => {
+                            // an expired log would only be removed again by the next
+                            // maintenance pass, and a live log that does not match yet is
+                            // looked at again once it has grown
+                            if matches!(outcome, FileOutcome::Filtered | FileOutcome::Expired) {
+                                self.processed.insert(path.clone(), modified);
+                                changed = true;
+                            }
+                            stats.files.push(FileResult::new(path, outcome));
+                            continue;
+                        }
+                    }
+                }
+                Err(e) if e.kind() == io::ErrorKind::PermissionDenied => {
+                    // nothing to back off for, and only worth telling once per file
+                    if self.unreadable.insert(path.clone()) {
+                        stats.warnings.push(anyhow::anyhow!(
+                            "skipping {}, as it is not readable; check its permissions",
+                            path.display()
+                        ));
+                    }
+                    stats
+                        .files
+                        .push(FileResult::new(path, FileOutcome::Unreadable));
+                    continue;
+                }
+                Err(e) if e.kind() == io::ErrorKind::InvalidData => {
+                    // the header is complete, so looked at again only once the log changes
+                    self.diagnostics
+                        .log(format_args!("skipping {}: {}", path.display(), e));
+                    stats
+                        .files
+                        .push(FileResult::new(path.clone(), FileOutcome::NoHeader));
+                    self.processed.insert(path, modified);
+                    changed = true;
+                    continue;
+                }
+                Err(e) => return Err(anyhow::Error::new(e).context(read_context())),
+            };
+            changed = true;
+            let keep = kept.contains(&path);
+            let (linked, archived_path) =
+                match self.archive(&logfile, date, keep, &mut stats.warnings) {
+                    Ok(archived) => archived,
+                    Err(e) if is_disk_full(&e) => {
+                        // see `check_free_space` for when this is lifted
+                        if !self.low_space {
+                            self.low_space = true;
+                            stats.warnings.push(anyhow::Error::new(e).context(
+                                "the collection drive is full, archiving is paused until \
+                                 space is freed",
+                            ));
+                        }
+                        stopped = true;
+                        break;
+                    }
+                    Err(e) => {
+                        return Err(anyhow::Error::new(e)
+                            .context(format!("failed to archive {}", file_name)))
+                    }
+                };
+            if linked {
+                stats.linked += 1;
+            }
+            let outcome = if keep {
+                FileOutcome::Kept
+            } else if self.collection.link_mode == LinkMode::Move
+                // a dry run never moves anything, but would have moved a closed log
+                && !self.collection.dry_run
+                && self.fs.exists(&logfile.path)
+            {
+                FileOutcome::InUse
+            } else if linked {
+                FileOutcome::Linked
+            } else {
+                FileOutcome::AlreadyArchived
+            };
+            stats.files.push(FileResult {
+                path,
+                outcome,
+                archived_path: Some(archived_path),
+            });
+            if matches!(outcome, FileOutcome::InUse | FileOutcome::Kept) {
+                // left in place, so try again next time, moving it once it can be
+                continue;
+            }
+            // looked at again until the session is over, for `create_link` to put the final
+            // size in the manifest. one that can no longer be opened has nothing to add
+            if !self.collection.dry_run && self.fs.is_in_use(&logfile.path).unwrap_or(false) {
+                continue;
+            }
+            self.processed.insert(logfile.path, modified);
+        }
+        if let Some(crash_dump_path) = self.crash_dump_path.clone().filter(|_| !stopped) {
+            match self.archive_crash_dumps(&crash_dump_path) {
+                Ok(0) => {}
+                Ok(archived) => {
+                    changed = true;
+                    self.diagnostics
+                        .log(format_args!("archived {} crash dump file(s)", archived));
+                }
+                // there is no folder until VRChat has crashed at least once
+                Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+                Err(e) => stats.warnings.push(anyhow::Error::new(e).context(format!(
+                    "failed to archive crash dumps from {}",
+                    crash_dump_path.display()
+                ))),
+            }
+        }
+        self.last_scan = Some(Local::now());
+        if changed && !self.collection.dry_run {
+            if let Err(e) = self.save_state() {
+                let e = anyhow::Error::new(e)
+                    .context(format!("failed to save {}", self.state_path.display()));
+                stats.warnings.push(e);
+            }
+        }
+        match scan_error {
+            Some(e) => Err(e),
+            None => Ok(stats),
+        }
+    }
+
+    /// The checks a logfile has to pass to be archived, wherever it was found. Returns it
+    /// along with its partition date, or why it is left alone for now. A header with an
+    /// invalid date fails with `InvalidData`, see `VRCLogfile::new`.
+    fn screen(
+        &self,
+        path: &Path,
+        modified: SystemTime,
+    ) -> io::Result<Result<(VRCLogfile, NaiveDate), FileOutcome>> {
+        // still being written, so wait for the session to end rather than archive
+        // a partial log (a timestamp in the future counts as recent as well)
+        if self.min_age > Duration::ZERO
+            && SystemTime::now()
+                .duration_since(modified)
+                .map_or(true, |age| age < self.min_age)
+        {
+            return Ok(Err(FileOutcome::Active));
+        }
+        let logfile = match VRCLogfile::new(&*self.fs, path.to_owned(), self.mmap_headers)? {
+            Some(logfile) => logfile,
+            None => return Ok(Err(FileOutcome::NoHeader)),
+        };
+        let date = self.partition_date(&logfile)?;
+        if let Some(ref re) = self.content_filter {
+            if !logfile.contains_match(&*self.fs, re, self.scan_max_bytes)? {
+                return Ok(Err(FileOutcome::Filtered));
+            }
+        }
+        if self.is_expired(date) {
+            return Ok(Err(FileOutcome::Expired));
+        }
+        Ok(Ok((logfile, date)))
+    }
+
+    /// Warns once when the drive archiving needs space on runs low, which is the collection's
+    /// when `copying` and VRChat's otherwise, and sets `low_space` until there is enough again.
+    fn check_free_space(&mut self, copying: bool, warnings: &mut Vec<anyhow::Error>) {
+        let path = if copying {
+            &self.collection.collection_path
+        } else {
+            &self.vrchats[0].vrchat_path
+        };
+        let free = match self.fs.free_space(path) {
+            Ok(free) => free,
+            Err(e) => {
+                self.diagnostics.log(format_args!(
+                    "failed to get the free space of {}: {}",
+                    path.display(),
+                    e
+                ));
+                return;
+            }
+        };
+        // a drive that filled up has to have some room again at the least
+        if free >= self.min_free_space.max(1) {
+            if self.low_space {
+                self.diagnostics
+                    .log(format_args!("free space recovered on {}", path.display()));
+            }
+            self.low_space = false;
+            return;
+        }
+        if self.low_space {
+            return;
+        }
+        self.low_space = true;
+        warnings.push(if copying {
+            anyhow::anyhow!(
+                "the collection drive has only {} free, below min_free_space_mb; archiving is \
+                 paused until space is freed",
+                format_size(free)
+            )
+        } else {
+            anyhow::anyhow!(
+                "the drive of the VRChat log folder has only {} free, below \
+                 min_free_space_mb; VRChat may fail to write its logs",
+                format_size(free)
+            )
+        });
+    }
+
+    /// Places `logfile` in the partition for `date` under the name it was archived as
+    /// before, if any, copying rather than moving it with `keep`. Returns where it went
+    /// and `true` if it was not there yet.
+    fn archive(
+        &mut self,
+        logfile: &VRCLogfile,
+        date: NaiveDate,
+        keep: bool,
+        warnings: &mut Vec<anyhow::Error>,
+    ) -> io::Result<(bool, PathBuf)> {
+        let identity = logfile.identity();
+        let file_name = match self.archived_names.get(&identity) {
+            Some(file_name) => file_name.clone(),
+            None => {
+                // a link keeps the name of what it points at
+                let wanted_name = match self.collection.link_mode {
+                    LinkMode::Copy | LinkMode::Move if self.timestamp_names => OsString::from(
+                        logfile
+                            .timestamp
+                            .format("%Y-%m-%d_%H-%M-%S.txt")
+                            .to_string(),
+                    ),
+                    _ => logfile.path.file_name().unwrap().to_owned(),
+                };
+                let world = if self.world_folders {
+                    logfile
+                        .first_world(&*self.fs, self.scan_max_bytes)?
+                        .as_deref()
+                        .and_then(world_folder_name)
+                } else {
+                    None
+                };
+                let wanted_name = match world {
+                    Some(world) => Path::new(&world).join(wanted_name).into_os_string(),
+                    None => wanted_name,
+                };
+                let file_name = self
+                    .collection
+                    .free_file_name(logfile, date, &wanted_name)?;
+                self.archived_names.insert(identity, file_name.clone());
+                file_name
+            }
+        };
+        if self.ntfs_compression && !self.collection.dry_run {
+            let partition_folder_path = self.collection.partition_folder_path(date);
+            if let Err(e) = self.fs.create_compressed_dir_all(&partition_folder_path) {
+                // e.g. FAT32 or exFAT, which is no reason to fail every poll
+                self.ntfs_compression = false;
+                warnings.push(anyhow::Error::new(e).context(
+                    "NTFS compression is not available for the collection, storing logs uncompressed",
+                ));
+            }
+        }
+        let linked = match self.collection.create_link(logfile, date, &file_name, keep) {
+            // creating symlinks needs SeCreateSymbolicLinkPrivilege, which only
+            // administrators have unless Developer Mode is on.
+            // copy instead of failing every poll, until the settings are saved again
+            Err(e)
+                if self.collection.link_mode == LinkMode::Symlink
+                    && e.raw_os_error() == Some(ERROR_PRIVILEGE_NOT_HELD as i32) =>
+            {
+                self.collection.link_mode = LinkMode::Copy;
+                warnings.push(anyhow::Error::new(e).context(
+                    "symbolic links require Developer Mode or administrator rights, copying logs instead",
+                ));
+                self.collection
+                    .create_link(logfile, date, &file_name, keep)?
+            }
+            result => result?,
+        };
+        let archived_path = self.collection.partition_folder_path(date).join(&file_name);
+        Ok((linked, archived_path))
+    }
+
+    /// Where `path` would be in the collection once archived, if it is there, whether plain
+    /// or compressed.
+    fn archived_path(&self, path: &Path) -> Result<Option<PathBuf>> {
+        let logfile = VRCLogfile::new(&*self.fs, path.to_owned(), false)
+            .with_context(|| format!("failed to read {}", path.display()))?
+            .ok_or_else(|| anyhow::anyhow!("no header found in {}", path.display()))?;
+        let date = self
+            .partition_date(&logfile)
+            .with_context(|| format!("failed to read {}", path.display()))?;
+        // the name it was archived under is only known from the state, otherwise it has to
+        // be one of the names `archive` would have picked first
+        let file_names = match self.archived_names.get(&logfile.identity()) {
+            Some(file_name) => vec![file_name.clone()],
+            None => vec![
+                logfile.path.file_name().unwrap().to_owned(),
+                OsString::from(
+                    logfile
+                        .timestamp
+                        .format("%Y-%m-%d_%H-%M-%S.txt")
+                        .to_string(),
+                ),
+            ],
+        };
+        let partition_folder_path = self.collection.partition_folder_path(date);
+        for file_name in file_names {
+            let archived_path = partition_folder_path.join(file_name);
+            let gz_path = append_extension(&archived_path, "gz");
+            let found = if self.fs.exists(&archived_path) {
+                archived_path.clone()
+            } else if self.fs.exists(&gz_path) {
+                gz_path
+            } else {
+                continue;
+            };
+            // given the plain name, this falls back to the sidecar once compressed
+            if !is_other_log(&*self.fs, &logfile, &archived_path)? {
+                return Ok(Some(found));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Archives the logs in `folder`, e.g. a backup of old ones, as if they had been found
+    /// in the VRChat folder. They are copied unless hard linked, as a symlink into the
+    /// folder would break along with it, and moving would empty it.
+    fn import(&mut self, folder: &Path) -> Result<ImportStats> {
+        let link_mode = self.collection.link_mode;
+        if link_mode != LinkMode::HardLink {
+            self.collection.link_mode = LinkMode::Copy;
+        }
+        let result = self.import_from(folder);
+        self.collection.link_mode = link_mode;
+        result
+    }
+
+    fn import_from(&mut self, folder: &Path) -> Result<ImportStats> {
+        let mut stats = ImportStats::default();
+        let diagnostics = &self.diagnostics;
+        let paths = LocalLowVRChat::new(folder.to_owned())
+            .list_logfile_paths(&*self.fs, &self.exclude, |path| {
+                diagnostics.log(format_args!("debug: excluded {}", path.display()));
+                stats.skipped += 1;
+            })
+            .with_context(|| format!("failed to scan {}", folder.display()))?;
+        for path in paths {
+            let read_context = || format!("failed to read {}", path.display());
+            let modified = self.fs.metadata(&path).with_context(read_context)?.modified;
+            let (logfile, date) = match self.screen(&path, modified) {
+                Ok(Ok(screened)) => screened,
+                Ok(Err(FileOutcome::NoHeader)) => {
+                    stats
+                        .warnings
+                        .push(anyhow::anyhow!("no header found in {}", path.display()));
+                    stats.skipped += 1;
+                    continue;
+                }
+                Ok(Err(_)) => {
+                    stats.skipped += 1;
+                    continue;
+                }
+                Err(e) if e.kind() == io::ErrorKind::PermissionDenied => {
+                    stats.warnings.push(anyhow::anyhow!(
+                        "skipping {}, as it is not readable",
+                        path.display()
+                    ));
+                    stats.skipped += 1;
+                    continue;
+                }
+                Err(e) if e.kind() == io::ErrorKind::InvalidData => {
+                    stats.warnings.push(
+                        anyhow::Error::new(e).context(format!("skipping {}", path.display())),
+                    );
+                    stats.skipped += 1;
+                    continue;
+                }
+                Err(e) => return Err(anyhow::Error::new(e).context(read_context())),
+            };
+            if self.archive(&logfile, date, false, &mut stats.warnings)?.0 {
+                stats.imported += 1;
+            } else {
+                stats.skipped += 1;
+            }
+        }
+        if !self.collection.dry_run {
+            self.save_state()
+                .with_context(|| format!("failed to save {}", self.state_path.display()))?;
+        }
+        Ok(stats)
+    }
+
+    fn save_state(&self) -> io::Result<()> {
+        let mut state = State::new(self.collection.collection_path.clone());
+        state.last_scan = self.last_scan;
+        state.processed = self.processed.clone();
+        state.archived_names = self
+            .archived_names
+            .iter()
+            .map(|(identity, file_name)| (*identity, file_name.to_string_lossy().into_owned()))
+            .collect();
+        state.notified = self.notified.clone();
+        state.save(&*self.fs, &self.state_path)
+    }
+
+    /// Whether `e` is worth a notification, i.e. has not been notified within the
+    /// cooldown. Remembers it as notified now if so.
+    fn should_notify(&mut self, e: &anyhow::Error) -> bool {
+        let cooldown = match chrono::Duration::from_std(self.notification_cooldown) {
+            Ok(cooldown) if cooldown > chrono::Duration::zero() => cooldown,
+            _ => return true,
+        };
+        let now = Local::now();
+        self.notified
+            .retain(|_, &mut notified_at| now - notified_at < cooldown);
+        let signature = format!("{:#}", e);
+        if self.notified.contains_key(&signature) {
+            return false;
+        }
+        self.notified.insert(signature, now);
+        if !self.collection.dry_run {
+            if let Err(e) = self.save_state() {
+                self.diagnostics.log(format_args!(
+                    "failed to save {}: {}",
+                    self.state_path.display(),
+                    e
+                ));
+            }
+        }
+        true
+    }
+
+    /// Copies the files of each crash folder in `crash_dump_path` into the partition of
+    /// the day of the crash, prefixed with the folder name, so that a crash stays with
+    /// the log of its session. Returns the number of files new to the collection.
+    fn archive_crash_dumps(&mut self, crash_dump_path: &Path) -> io::Result<usize> {
+        let mut archived = 0;
+        for (folder_path, is_dir) in self.fs.read_dir(crash_dump_path)? {
+            if !is_dir {
+                continue;
+            }
+            let metadata = self.fs.metadata(&folder_path)?;
+            // all of a crash goes into one partition, even if written across midnight
+            let crashed_at = metadata.created.unwrap_or(metadata.modified);
+            let date = self.local_date(crashed_at.into());
+            for path in self.fs.list_files(&folder_path)? {
+                let modified = self.fs.metadata(&path)?.modified;
+                if self.processed.get(&path) == Some(&modified)
+                    || SystemTime::now()
+                        .duration_since(modified)
+                        .map_or(true, |age| age < CRASH_DUMP_MIN_AGE)
+                {
+                    continue;
+                }
+                let mut file_name = folder_path.file_name().unwrap().to_owned();
+                file_name.push("_");
+                file_name.push(path.file_name().unwrap());
+                if self
+                    .collection
+                    .archive_artifact(&path, date, CRASH_DUMPS_FOLDER, &file_name)?
+                {
+                    archived += 1;
+                }
+                self.processed.insert(path, modified);
+            }
+        }
+        Ok(archived)
+    }
+
+    fn local_date(&self, datetime: DateTime<Local>) -> NaiveDate {
+        local_date(datetime, self.utc_offset)
+    }
+
+    /// The date `partition_folder_path` is keyed on: either the header timestamp or the
+    /// creation time, seen in `utc_offset` if given and in the system time zone otherwise.
+    fn partition_date(&self, logfile: &VRCLogfile) -> io::Result<NaiveDate> {
+        let datetime = match self.partition_date_source {
+            PartitionDate::Header => match self.utc_offset {
+                None => return Ok(logfile.date()),
+                Some(_) => match Local.from_local_datetime(&logfile.timestamp).earliest() {
+                    Some(datetime) => datetime,
+                    // the header time fell into a DST gap, nothing to shift
+                    None => return Ok(logfile.date()),
+                },
+            },
+            PartitionDate::Created => match self.fs.metadata(&logfile.path)?.created {
+                Some(created) => DateTime::from(created),
+                None => {
+                    return Err(io::Error::new(
+                        io::ErrorKind::Unsupported,
+                        "the drive keeps no creation times",
+                    ))
+                }
+            },
+        };
+        Ok(self.local_date(datetime))
+    }
+
+    fn retention_cutoff(&self) -> Option<NaiveDate> {
+        let today = self.local_date(Local::now());
+        self.retention_days
+            .map(|days| today - chrono::Duration::days(days.into()))
+    }
+
+    fn is_expired(&self, date: NaiveDate) -> bool {
+        self.retention_cutoff().is_some_and(|cutoff| date < cutoff)
+    }
+
+    /// Runs housekeeping on the collection at most once a day.
+    fn maintain(&mut self) -> Result<()> {
+        let today = self.local_date(Local::now());
+        if self.last_maintenance == Some(today) {
+            return Ok(());
+        }
+        self.last_maintenance = Some(today);
+        if let Some(cutoff) = self.retention_cutoff() {
+            self.collection.remove_partitions_before(cutoff)?;
+        }
+        // before compressing, which would leave nothing to read the headers of
+        if let Some(gap) = self.merge_gap {
+            for (date, _) in self.collection.partition_folders()? {
+                // today's logs may still be split further
+                if date < today {
+                    self.collection.merge_split_logs(date, gap)?;
+                }
+            }
+        }
+        if let Some(days) = self.compress_after_days {
+            let cutoff = today - chrono::Duration::days(days.into());
+            for (date, _) in self.collection.partition_folders()? {
+                if date < cutoff {
+                    self.collection.compress_partition(date)?;
+                }
+            }
+        }
+        self.collection.check_archive_index()?;
+        // e.g. left behind by `Move` mode or a partition template that changed
+        let removed = self.collection.remove_empty_folders(EMPTY_FOLDER_MIN_AGE)?;
+        if removed > 0 {
+            self.diagnostics
+                .log(format_args!("removed {} empty folder(s)", removed));
+        }
+        Ok(())
+    }
+
+    /// Applies the settings that can change while running.
+    fn apply_config(&mut self, config: &Config) {
+        // also gives symlinks another try after falling back to copying
+        self.collection.link_mode = config.link_mode;
+        self.collection.flat_mirror = config.flat_mirror;
+        self.retention_days = config.retention_days;
+        self.compress_after_days = config.compress_after_days;
+        self.content_filter = config.content_filter();
+        self.scan_max_bytes = config.scan_max_bytes;
+        self.merge_gap = config.merge_gap();
+        self.min_age = config.min_age();
+        self.keep_recent = config.keep_recent;
+        self.min_free_space = config.min_free_space();
+        if config.archive_existing {
+            self.started_at = None;
+        } else if self.started_at.is_none() {
+            // turned off while running, so it takes effect from now on
+            self.started_at = Some(SystemTime::now());
+        }
+        self.ntfs_compression = config.ntfs_compression;
+        self.timestamp_names = config.timestamp_names;
+        self.world_folders = config.world_folders;
+        self.exclude = config.exclude();
+        self.excluded.clear();
+        self.notification_cooldown = config.notification_cooldown();
+        self.crash_dump_path = config.crash_dump_dir();
+        // let the new retention settings take effect right away
+        self.last_maintenance = None;
+    }
+
+    /// Watches each source that can be, leaving the others to the periodic sweep.
+    fn watch(&self) -> Vec<DirectoryWatcher> {
+        self.vrchats
+            .iter()
+            .filter_map(|vrchat| match DirectoryWatcher::new(&vrchat.vrchat_path) {
+                Ok(watcher) => Some(watcher),
+                Err(e) => {
+                    self.diagnostics.log(format_args!(
+                        "failed to watch {}: {}",
+                        vrchat.vrchat_path.display(),
+                        e
+                    ));
+                    None
+                }
+            })
+            .collect()
+    }
+
+    /// Reconstructs what is known about the collection from its contents, which takes a
+    /// while for a large backlog.
+    fn rebuild_state(collection: &UnrotateCollection, vrchats: &[LocalLowVRChat]) -> State {
+        let mut state = State::new(collection.collection_path.clone());
+        for (partition_folder_path, archived_path) in
+            collection.archived_logfile_paths().unwrap_or_default()
+        {
+            // a merged log has the header of the first of its parts
+            if is_artifact(&archived_path) {
+                continue;
+            }
+            if archived_path.extension() == Some("txt".as_ref()) {
+                if let Ok(Some(logfile)) =
+                    VRCLogfile::new(&*collection.fs, archived_path.clone(), false)
+                {
+                    // including the world folder, if any
+                    let file_name = archived_path
+                        .strip_prefix(&partition_folder_path)
+                        .unwrap()
+                        .to_string_lossy();
+                    state
+                        .archived_names
+                        .push((logfile.identity(), file_name.into_owned()));
+                }
+            }
+            // an archived file with the same modified time as its source is already up to date
+            let modified = match collection.fs.metadata(&archived_path) {
+                Ok(metadata) => metadata.modified,
+                Err(_) => continue,
+            };
+            let file_name = archived_path.file_name().unwrap();
+            for vrchat in vrchats {
+                state
+                    .processed
+                    .insert(vrchat.vrchat_path.join(file_name), modified);
+            }
+        }
+        state
+    }
+
+    fn new(locallow: &Path, config: &Config) -> Self {
+        Self::with_fs(locallow, config, Arc::new(OsFileSystem))
+    }
+
+    /// Like `new`, but with everything archiving does to the drive going to `fs`.
+    fn with_fs(locallow: &Path, config: &Config, fs: Arc<dyn FileSystem + Send + Sync>) -> Self {
+        LOG_PATTERNS.get_or_init(|| LogPatterns::from_config(config));
+        let vrchats = LocalLowVRChat::from_config(locallow, config);
+        let mut collection = match config.collection_path {
+            Some(ref collection_path) => {
+                UnrotateCollection::new(collection_path.clone(), config.link_mode)
+            }
+            None => UnrotateCollection::with_locallow_path(locallow, config.link_mode),
+        };
+        if config.dedupe {
+            collection.enable_dedupe();
+        }
+        if config.archive_index {
+            collection.enable_archive_index();
+        }
+        collection.partition_template = config.partition_template();
+        collection.flat_mirror = config.flat_mirror;
+        collection.fs = fs.clone();
+        let state_path = State::path(locallow);
+        let state = State::load(&*fs, &state_path)
+            .filter(|state| state.collection_path == collection.collection_path)
+            .unwrap_or_else(|| Self::rebuild_state(&collection, &vrchats));
+        let archived_names = state
+            .archived_names
+            .into_iter()
+            .map(|(identity, file_name)| (identity, OsString::from(file_name)))
+            .collect();
+        Self {
+            vrchats,
+            collection,
+            processed: state.processed,
+            archived_names,
+            missing_sources: HashSet::new(),
+            layout_checked: false,
+            unreadable: HashSet::new(),
+            state_path,
+            last_scan: state.last_scan,
+            retention_days: config.retention_days,
+            compress_after_days: config.compress_after_days,
+            last_maintenance: None,
+            partition_date_source: config.partition_date,
+            utc_offset: config.utc_offset(),
+            content_filter: config.content_filter(),
+            scan_max_bytes: config.scan_max_bytes,
+            merge_gap: config.merge_gap(),
+            mmap_headers: config.mmap_headers,
+            started_at: if config.archive_existing {
+                None
+            } else {
+                Some(SystemTime::now())
+            },
+            min_age: config.min_age(),
+            keep_recent: config.keep_recent,
+            min_free_space: config.min_free_space(),
+            low_space: false,
+            ntfs_compression: config.ntfs_compression,
+            timestamp_names: config.timestamp_names,
+            world_folders: config.world_folders,
+            exclude: config.exclude(),
+            excluded: HashSet::new(),
+            notification_cooldown: config.notification_cooldown(),
+            crash_dump_path: config.crash_dump_dir(),
+            notified: state.notified,
+            diagnostics: Diagnostics::with_locallow_path(locallow),
+            fs,
+        }
+    }
+}
+
+/// Checks, in the order of `self_test_checks`, what archiving relies on: finding
+/// LocalLow, reading the log folders, creating the collection and hard linking from
+/// the first log folder into it.
+fn self_test(locallow: &Path, config: &Config, collection_path: &Path) -> [Result<()>; 4] {
+    const TEST_FILE_NAME: &str = "VRCLogUnrotate self-test.tmp";
+    let resolved = get_appdata_locallow()
+        .map(drop)
+        .ok_or_else(|| anyhow::anyhow!("failed to get LocalAppDataLow path"));
+    let vrchats = LocalLowVRChat::from_config(locallow, config);
+    let readable = vrchats.iter().try_for_each(|vrchat| {
+        let path = &vrchat.vrchat_path;
+        path.read_dir()
+            .map(drop)
+            .with_context(|| format!("failed to read {}", path.display()))
+    });
+    let created = fs::create_dir_all(collection_path)
+        .with_context(|| format!("failed to create {}", collection_path.display()));
+    let linked = if readable.is_err() || created.is_err() {
+        Err(anyhow::anyhow!("not tried, as a folder is missing"))
+    } else {
+        let source_path = vrchats[0].vrchat_path.join(TEST_FILE_NAME);
+        let link_path = collection_path.join(TEST_FILE_NAME);
+        let result = fs::write(&source_path, b"")
+            .with_context(|| format!("failed to create {}", source_path.display()))
+            .and_then(|_| {
+                fs::hard_link(&source_path, &link_path).map_err(|e| {
+                    let context = if e.raw_os_error() == Some(ERROR_NOT_SAME_DEVICE as i32) {
+                        "the collection is on another drive than the VRChat folder, so logs are \
+                         copied instead"
+                    } else {
+                        "failed to create a hard link"
+                    };
+                    anyhow::Error::new(e).context(context)
+                })
+            });
+        let _ = fs::remove_file(&link_path);
+        let _ = fs::remove_file(&source_path);
+        result
+    };
+    [resolved, readable, created, linked]
+}
+
+/// Runs a single sweep without the tray UI.
+fn run_once(dry_run: bool) -> Result<StepStats> {
+    let locallow = get_appdata_locallow()
+        .ok_or_else(|| anyhow::anyhow!("failed to get LocalAppDataLow path"))?;
+    let config = Config::with_locallow_path(&locallow)?;
+    let mut unrotate = Unrotate::new(&locallow, &config);
+    unrotate.collection.dry_run = dry_run;
+    unrotate.step(|_, _| ControlFlow::Continue(()))
+}
+
+fn run_import(folder: &Path) -> Result<ImportStats> {
+    let locallow = get_appdata_locallow()
+        .ok_or_else(|| anyhow::anyhow!("failed to get LocalAppDataLow path"))?;
+    let config = Config::with_locallow_path(&locallow)?;
+    let mut unrotate = Unrotate::new(&locallow, &config);
+    unrotate.import(folder)
+}
+
+fn run_reorganize() -> Result<ReorganizeStats> {
+    let locallow = get_appdata_locallow()
+        .ok_or_else(|| anyhow::anyhow!("failed to get LocalAppDataLow path"))?;
+    let config = Config::with_locallow_path(&locallow)?;
+    let unrotate = Unrotate::new(&locallow, &config);
+    unrotate.collection.reorganize()
+}
+
+/// Where the log at `path` is archived, if it is.
+fn run_status(path: &Path) -> Result<Option<PathBuf>> {
+    let locallow = get_appdata_locallow()
+        .ok_or_else(|| anyhow::anyhow!("failed to get LocalAppDataLow path"))?;
+    let config = Config::with_locallow_path(&locallow)?;
+    let unrotate = Unrotate::new(&locallow, &config);
+    // compared with the original path in the sidecar of a compressed log
+    let path = std::path::absolute(path)?;
+    unrotate.archived_path(&path)
+}
+
+/// The logs of the sessions that started from `from` through `to`, as found in the
+/// archive index.
+fn run_sessions(from: NaiveDate, to: NaiveDate) -> Result<Vec<IndexEntry>> {
+    let locallow = get_appdata_locallow()
+        .ok_or_else(|| anyhow::anyhow!("failed to get LocalAppDataLow path"))?;
+    let config = Config::with_locallow_path(&locallow)?;
+    let unrotate = Unrotate::new(&locallow, &config);
+    let collection = &unrotate.collection;
+    let archive_index = collection
+        .archive_index
+        .as_ref()
+        .ok_or_else(|| anyhow::anyhow!("archive_index is off in the config"))?;
+    collection
+        .check_archive_index()
+        .context("failed to rebuild the archive index")?;
+    let mut entries = archive_index
+        .between(from, to)
+        .context("failed to read the archive index")?;
+    for entry in &mut entries {
+        entry.archived_path = collection.collection_path.join(&entry.archived_path);
+    }
+    Ok(entries)
+}
+
+/// Removes what the app leaves behind: the Run registry value and everything under
+/// LocalLow, except for the collection unless `purge_archive` is set and confirmed.
+fn uninstall(purge_archive: bool) -> Result<()> {
+    let locallow = get_appdata_locallow()
+        .ok_or_else(|| anyhow::anyhow!("failed to get LocalAppDataLow path"))?;
+    let config = match Config::with_locallow_path(&locallow) {
+        Ok(config) => config,
+        // the collection could be anywhere, so do not guess which folder to delete
+        Err(e) if purge_archive => return Err(e.context("cannot tell where the collection is")),
+        Err(_) => Config::default(),
+    };
+    let collection_path = config.collection_path.clone().unwrap_or_else(|| {
+        UnrotateCollection::with_locallow_path(&locallow, config.link_mode).collection_path
+    });
+    autostart::disable().context("failed to remove the Run registry value")?;
+    println!("removed the Run registry value");
+    if purge_archive && collection_path.exists() {
+        let locale = config.locale.unwrap_or_else(Locale::detect);
+        let text = (locale.strings().purge_confirm)(&collection_path.display().to_string());
+        if confirm(&text) {
+            fs::remove_dir_all(&collection_path)
+                .with_context(|| format!("failed to remove {}", collection_path.display()))?;
+            println!("removed {}", collection_path.display());
+        } else {
+            println!("kept {}", collection_path.display());
+        }
+    }
+    if let Some(ref portable_path) = *PORTABLE_PATH {
+        // the executable lives there too, alongside whatever else is on the drive
+        println!(
+            "portable mode: left {} as is, delete it to remove everything",
+            portable_path.display()
+        );
+        return Ok(());
+    }
+    let app_path = app_data_path(&locallow);
+    let entries = match app_path.read_dir() {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(()),
+        Err(e) => return Err(e.into()),
+    };
+    for entry in entries {
+        let path = entry?.path();
+        // the default collection lives in here as well
+        if collection_path.starts_with(&path) {
+            continue;
+        }
+        let result = if path.is_dir() {
+            fs::remove_dir_all(&path)
+        } else {
+            fs::remove_file(&path)
+        };
+        result.with_context(|| format!("failed to remove {}", path.display()))?;
+        println!("removed {}", path.display());
+    }
+    // only succeeds once nothing is left
+    if fs::remove_dir(&app_path).is_ok() {
+        let _ = fs::remove_dir(locallow.join(VENDOR_NAME));
+    }
+    Ok(())
+}
+
+/// Asks a yes/no question in a message box, since a console attached to a GUI app
+/// cannot be read from reliably. Defaults to no.
+fn confirm(text: &str) -> bool {
+    use std::os::windows::ffi::OsStrExt;
+    use std::{iter, ptr};
+    let to_wide =
+        |s: &str| -> Vec<u16> { OsStr::new(s).encode_wide().chain(iter::once(0)).collect() };
+    let text = to_wide(text);
+    let caption = to_wide(APP_NAME);
+    let answer = unsafe {
+        MessageBoxW(
+            ptr::null_mut(),
+            text.as_ptr(),
+            caption.as_ptr(),
+            MB_YESNO | MB_ICONWARNING | MB_DEFBUTTON2,
+        )
+    };
+    answer == IDYES
+}
+
+fn main() {
+    let args: Vec<_> = std::env::args().skip(1).collect();
+    if let Some(index) = args.iter().position(|arg| arg == "--import") {
+        unsafe { AttachConsole(ATTACH_PARENT_PROCESS) };
+        let folder = match args.get(index + 1) {
+            Some(folder) => PathBuf::from(folder),
+            None => {
+                eprintln!("usage: --import <folder>");
+                std::process::exit(2);
+            }
+        };
+        match run_import(&folder) {
+            Ok(stats) => {
+                for warning in &stats.warnings {
+                    eprintln!("warning: {:#}", warning);
+                }
+                println!("imported {}, skipped {}", stats.imported, stats.skipped);
+            }
+            Err(e) => {
+                eprintln!("{:?}", e);
+                std::process::exit(1);
+            }
+        }
+        return;
+    }
+    if let Some(index) = args.iter().position(|arg| arg == "--status") {
+        unsafe { AttachConsole(ATTACH_PARENT_PROCESS) };
+        let path = match args.get(index + 1) {
+            Some(path) => PathBuf::from(path),
+            None => {
+                eprintln!("usage: --status <logfile>");
+                std::process::exit(2);
+            }
+        };
+        // like grep: 0 if archived, 1 if not, 2 if that cannot be told
+        match run_status(&path) {
+            Ok(Some(archived_path)) => println!("{}", archived_path.display()),
+            Ok(None) => {
+                println!("not archived");
+                std::process::exit(1);
+            }
+            Err(e) => {
+                eprintln!("{:?}", e);
+                std::process::exit(2);
+            }
+        }
+        return;
+    }
+    if let Some(index) = args.iter().position(|arg| arg == "--sessions") {
+        unsafe { AttachConsole(ATTACH_PARENT_PROCESS) };
+        let date = |index| {
+            args.get(index)
+                .and_then(|arg: &String| NaiveDate::parse_from_str(arg, "%Y-%m-%d").ok())
+        };
+        let (from, to) = match (date(index + 1), date(index + 2)) {
+            (Some(from), Some(to)) => (from, to),
+            _ => {
+                eprintln!("usage: --sessions <yyyy-mm-dd> <yyyy-mm-dd>");
+                std::process::exit(2);
+            }
+        };
+        match run_sessions(from, to) {
+            Ok(entries) => {
+                for entry in entries {
+                    println!(
+                        "{}\t{}",
+                        entry.first_timestamp,
+                        entry.archived_path.display()
+                    );
+                }
+            }
+            Err(e) => {
+                eprintln!("{:?}", e);
+                std::process::exit(1);
+            }
+        }
+        return;
+    }
+    if args.iter().any(|arg| arg == "--reorganize") {
+        unsafe { AttachConsole(ATTACH_PARENT_PROCESS) };
+        match run_reorganize() {
+            Ok(stats) => {
+                for path in &stats.conflicts {
+                    eprintln!(
+                        "warning: left {}, as another file is in its place",
+                        path.display()
+                    );
+                }
+                println!("moved {}", stats.moved);
+                if !stats.conflicts.is_empty() {
+                    std::process::exit(1);
+                }
+            }
+            Err(e) => {
+                eprintln!("{:?}", e);
+                std::process::exit(1);
+            }
+        }
+        return;
+    }
+    if args.iter().any(|arg| arg == "--uninstall") {
+        unsafe { AttachConsole(ATTACH_PARENT_PROCESS) };
+        let purge_archive = args.iter().any(|arg| arg == "--purge-archive");
+        if let Err(e) = uninstall(purge_archive) {
+            eprintln!("{:?}", e);
+            std::process::exit(1);
+        }
+        return;
+    }
+    let once = args.iter().any(|arg| arg == "--once");
+    let dry_run = args.iter().any(|arg| arg == "--dry-run");
+    if once || dry_run {
+        // as a GUI subsystem app we have no console of our own, so borrow the one we were started from
+        unsafe { AttachConsole(ATTACH_PARENT_PROCESS) };
+        let json = args.iter().any(|arg| arg == "--json");
+        let result = run_once(dry_run);
+        if json {
+            let (stats, error) = match result {
+                Ok(stats) => (stats, None),
+                Err(e) => (StepStats::default(), Some(e)),
+            };
+            let summary = JsonSummary {
+                dry_run,
+                scanned: stats.scanned,
+                linked: stats.linked,
+                skipped: stats.scanned - stats.linked,
+                files: &stats.files,
+                errors: stats
+                    .warnings
+                    .iter()
+                    .chain(&error)
+                    .map(|e| format!("{:#}", e))
+                    .collect(),
+            };
+            println!("{}", serde_json::to_string_pretty(&summary).unwrap());
+            if !summary.errors.is_empty() {
+                std::process::exit(1);
+            }
+            return;
+        }
+        match result {
+            Ok(stats) => {
+                let would_link = stats
+                    .files
+                    .iter()
+                    .filter(|file| dry_run && file.outcome == FileOutcome::Linked);
+                for file in would_link {
+                    if let Some(ref archived_path) = file.archived_path {
+                        println!(
+                            "would link {} -> {}",
+                            file.path.display(),
+                            archived_path.display()
+                        );
+                    }
+                }
+                for warning in &stats.warnings {
+                    eprintln!("warning: {:#}", warning);
+                }
+                println!("{}", stats.linked);
+            }
+            Err(e) => {
+                eprintln!("{:?}", e);
+                std::process::exit(1);
+            }
+        }
+        return;
+    }
+
+    let default_hook = panic::take_hook();
+    panic::set_hook(Box::new(move |info| {
+        if let Ok(mut last_panic) = LAST_PANIC.lock() {
+            *last_panic = Some(info.to_string());
+        }
+        default_hook(info);
+    }));
+
+    nwg::init().expect("Failed to init Native Windows GUI");
+    let locallow = match get_appdata_locallow() {
+        Some(locallow) => locallow,
+        None => {
+            // the config lives under LocalLow as well, so only the system language is known
+            let strings = Locale::detect().strings();
+            nwg::error_message(APP_NAME, strings.locallow_missing);
+            std::process::exit(1);
+        }
+    };
+    let diagnostics = Diagnostics::with_locallow_path(&locallow);
+    // two workers would race on the same files, as when both the Run key and the user
+    // start the app
+    let instance = match Instance::acquire(&app_data_path(&locallow)) {
+        Ok(Some(instance)) => Some(instance),
+        Ok(None) => return,
+        Err(e) => {
+            diagnostics.log(format_args!("failed to check for another instance: {}", e));
+            None
+        }
+    };
+    let config = match Config::with_locallow_path(&locallow) {
+        Ok(config) => config,
+        Err(e) => {
+            diagnostics.log(format_args!("startup error: {:#}", e));
+            // whatever the file says about the locale is in doubt as well
+            let strings = Locale::detect().strings();
+            let content = format!("{:#}\n\n{}", e, strings.config_invalid_fallback);
+            let params = nwg::MessageParams {
+                title: strings.settings_invalid,
+                content: &content,
+                buttons: nwg::MessageButtons::YesNo,
+                icons: nwg::MessageIcons::Warning,
+            };
+            if nwg::message(&params) != nwg::MessageChoice::Yes {
+                std::process::exit(1);
+            }
+            Config::default()
+        }
+    };
+    let locale = config.locale.unwrap_or_else(Locale::detect);
+    let tray = SystemTray {
+        locallow,
+        diagnostics,
+        config: RefCell::new(config),
+        locale,
+        ..Default::default()
+    };
+    let ui = SystemTray::build_ui(tray).expect("Failed to build UI");
+    if let Some(instance) = instance {
+        let show_menu_notifier = ui.show_menu_notice.sender();
+        thread::spawn(move || {
+            while instance.wait_for_show() {
+                show_menu_notifier.notice();
+            }
+        });
+    }
+    nwg::dispatch_thread_events();
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::Ordering;
+
+    use super::*;
+    use crate::filesystem::FakeFileSystem;
+
+    /// A folder of its own under `%TEMP%` for each test, removed afterwards.
+    struct TempDir(PathBuf);
+
+    impl TempDir {
+        fn new(name: &str) -> Self {
+            let path = std::env::temp_dir().join(format!(
+                "{}-test-{}-{}",
+                APP_NAME,
+                std::process::id(),
+                name
+            ));
+            let _ = fs::remove_dir_all(&path);
+            fs::create_dir_all(&path).unwrap();
+            Self(path)
+        }
+    }
+
+    impl Drop for TempDir {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.0);
+        }
+    }
+
+    impl std::ops::Deref for TempDir {
+        type Target = Path;
+
+        fn deref(&self) -> &Path {
+            &self.0
+        }
+    }
+
+    fn set_modified(path: &Path, modified: SystemTime) {
+        let file = OpenOptions::new().write(true).open(path).unwrap();
+        file.set_times(FileTimes::new().set_modified(modified))
+            .unwrap();
+    }
+
+    fn set_folder_modified(path: &Path, modified: SystemTime) {
+        use winapi::um::{winbase::FILE_FLAG_BACKUP_SEMANTICS, winnt::FILE_WRITE_ATTRIBUTES};
+        // a folder can only be opened with backup semantics
+        let folder = OpenOptions::new()
+            .access_mode(FILE_WRITE_ATTRIBUTES)
+            .custom_flags(FILE_FLAG_BACKUP_SEMANTICS)
+            .open(path)
+            .unwrap();
+        folder
+            .set_times(FileTimes::new().set_modified(modified))
+            .unwrap();
+    }
+
+    #[test]
+    fn remove_empty_folders_keeps_recent_and_non_empty_ones() {
+        let dir = TempDir::new("remove-empty-folders");
+        let collection = UnrotateCollection::new(dir.to_path_buf(), LinkMode::Copy);
+        let old = dir.join("2020").join("2020-01").join("01");
+        let non_empty = dir.join("2020").join("2020-01").join("02");
+        let recent = dir.join("2020").join("2020-02");
+        for path in [&old, &non_empty, &recent] {
+            fs::create_dir_all(path).unwrap();
+        }
+        fs::write(non_empty.join("output_log_12-34-56.txt"), b"").unwrap();
+        let long_ago = SystemTime::now() - Duration::from_secs(24 * 60 * 60);
+        // innermost first, as creating a folder counts as modifying its parent
+        for path in [&old, &non_empty, &dir.join("2020").join("2020-01")] {
+            set_folder_modified(path, long_ago);
+        }
+        let removed = collection
+            .remove_empty_folders(Duration::from_secs(60 * 60))
+            .unwrap();
+        assert_eq!(removed, 1);
+        assert!(!old.exists());
+        assert!(non_empty.join("output_log_12-34-56.txt").exists());
+        assert!(recent.is_dir());
+
+        // nested empty folders all go at once, but never the collection itself
+        fs::remove_file(non_empty.join("output_log_12-34-56.txt")).unwrap();
+        for path in [&non_empty, &recent, &dir.join("2020").join("2020-01")] {
+            set_folder_modified(path, long_ago);
+        }
+        set_folder_modified(&dir.join("2020"), long_ago);
+        let removed = collection
+            .remove_empty_folders(Duration::from_secs(60 * 60))
+            .unwrap();
+        assert_eq!(removed, 4);
+        assert!(dir.is_dir());
+        assert_eq!(dir.read_dir().unwrap().count(), 0);
+    }
+
+    /// Writes a log whose header is `first_line` through `fs`, returning it as read back.
+    fn write_log(fs: &dyn FileSystem, path: &Path, first_line: &str) -> VRCLogfile {
+        fs.create_dir_all(path.parent().unwrap()).unwrap();
+        fs.write(
+            path,
+            format!("{}\n2024.01.31 12:35:00 Log - more\n", first_line).as_bytes(),
+        )
+        .unwrap();
+        VRCLogfile::new(fs, path.to_owned(), false)
+            .unwrap()
+            .unwrap()
+    }
+
+    /// Where the tests on a `FakeFileSystem` keep everything.
+    fn fake_dir() -> PathBuf {
+        PathBuf::from(r"C:\fake")
+    }
+
+    #[test]
+    fn free_file_name_numbers_only_a_different_log() {
+        let dir = fake_dir();
+        let fs = Arc::new(FakeFileSystem::new());
+        let mut collection = UnrotateCollection::new(dir.join("collection"), LinkMode::Copy);
+        collection.fs = fs.clone();
+        let date = NaiveDate::from_ymd_opt(2024, 1, 31).unwrap();
+        let file_name = OsStr::new("output_log_2024-01-31_12-34-56.txt");
+        let first = write_log(
+            &*fs,
+            &dir.join("a").join(file_name),
+            "2024.01.31 12:34:56 Log - first",
+        );
+        let second = write_log(
+            &*fs,
+            &dir.join("b").join(file_name),
+            "2024.01.31 12:34:56 Log - second",
+        );
+        assert_eq!(
+            collection.free_file_name(&first, date, file_name).unwrap(),
+            file_name
+        );
+        assert!(collection
+            .create_link(&first, date, file_name, false)
+            .unwrap());
+        // the same log keeps its name, to be updated in place
+        assert_eq!(
+            collection.free_file_name(&first, date, file_name).unwrap(),
+            file_name
+        );
+        assert_eq!(
+            collection.free_file_name(&second, date, file_name).unwrap(),
+            "output_log_2024-01-31_12-34-56_2.txt"
+        );
+    }
+
+    #[test]
+    fn reorganize_merges_the_manifest_into_the_one_already_there() {
+        let dir = TempDir::new("reorganize-manifest");
+        let mut collection = UnrotateCollection::new(dir.join("collection"), LinkMode::Copy);
+        let date = NaiveDate::from_ymd_opt(2024, 1, 31).unwrap();
+        let old_name = OsStr::new("output_log_2024-01-31_12-34-56.txt");
+        let new_name = OsStr::new("output_log_2024-01-31_20-00-00.txt");
+        let old = write_log(&OsFileSystem, &dir.join("VRChat").join(old_name), HEADER);
+        let new = write_log(
+            &OsFileSystem,
+            &dir.join("VRChat").join(new_name),
+            "2024.01.31 20:00:00 Log - later",
+        );
+        collection.partition_template = PartitionTemplate::new(partition::YEARLY_TEMPLATE).unwrap();
+        assert!(collection.create_link(&old, date, old_name, false).unwrap());
+        let old_partition_path = collection.partition_folder_path(date);
+        collection.partition_template = PartitionTemplate::default();
+        assert!(collection.create_link(&new, date, new_name, false).unwrap());
+        let new_partition_path = collection.partition_folder_path(date);
+
+        let stats = collection.reorganize().unwrap();
+        assert_eq!(stats.conflicts, Vec::<PathBuf>::new());
+        // the log and its sidecar
+        assert_eq!(stats.moved, 2);
+        assert!(new_partition_path.join(old_name).exists());
+        assert!(!old_partition_path.exists());
+        let mut file_names: Vec<_> = Manifest::load(&OsFileSystem, &new_partition_path)
+            .unwrap()
+            .entries
+            .into_iter()
+            .map(|entry| entry.file_name)
+            .collect();
+        file_names.sort();
+        assert_eq!(
+            file_names,
+            [
+                old_name.to_str().unwrap().to_string(),
+                new_name.to_str().unwrap().to_string()
+            ]
+        );
+    }
+
+    #[test]
+    fn compress_partition_points_the_flat_link_at_the_compressed_log() {
+        let dir = TempDir::new("compress-flat");
+        let mut collection = UnrotateCollection::new(dir.join("collection"), LinkMode::Copy);
+        collection.flat_mirror = true;
+        let date = NaiveDate::from_ymd_opt(2024, 1, 31).unwrap();
+        let file_name = OsStr::new("output_log_2024-01-31_12-34-56.txt");
+        let logfile = write_log(&OsFileSystem, &dir.join("VRChat").join(file_name), HEADER);
+        assert!(collection
+            .create_link(&logfile, date, file_name, false)
+            .unwrap());
+        let archived_path = collection.partition_folder_path(date).join(file_name);
+        assert!(collection.flat_path(&archived_path, date).exists());
+
+        collection.compress_partition(date).unwrap();
+        let flat_names: Vec<_> = dir
+            .join("collection")
+            .join(FLAT_FOLDER)
+            .read_dir()
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name())
+            .collect();
+        assert_eq!(
+            flat_names,
+            ["2024-01-31_output_log_2024-01-31_12-34-56.txt.gz"]
+        );
+        assert_eq!(
+            fs::read(collection.flat_path(&append_extension(&archived_path, "gz"), date)).unwrap(),
+            fs::read(append_extension(&archived_path, "gz")).unwrap()
+        );
+    }
+
+    const HEADER: &str = "2024.01.31 12:34:56 Log        -  [Behaviour] Initialized PlayerAPI";
+
+    /// Copies from `fake_dir()/VRChat` into `fake_dir()/collection`, all within `fs`.
+    fn test_unrotate(config: Config, fs: &Arc<FakeFileSystem>) -> Unrotate {
+        let dir = fake_dir();
+        let config = Config {
+            vrchat_log_dir: Some(dir.join("VRChat")),
+            collection_path: Some(dir.join("collection")),
+            link_mode: LinkMode::Copy,
+            ..config
+        };
+        fs.create_dir_all(&dir.join("VRChat")).unwrap();
+        let mut unrotate = Unrotate::with_fs(&dir.join("LocalLow"), &config, fs.clone());
+        // the only file it would still write to the drive
+        unrotate.diagnostics = Diagnostics::default();
+        unrotate
+    }
+
+    fn sweep(unrotate: &mut Unrotate) -> StepStats {
+        unrotate.step(|_, _| ControlFlow::Continue(())).unwrap()
+    }
+
+    fn outcome(stats: &StepStats, path: &Path) -> Option<FileOutcome> {
+        stats
+            .files
+            .iter()
+            .find(|file| file.path == path)
+            .map(|file| file.outcome)
+    }
+
+    #[test]
+    fn step_goes_on_past_a_log_gone_before_it_is_looked_at() {
+        let dir = fake_dir();
+        let log_path = dir
+            .join("VRChat")
+            .join("output_log_2024-01-31_12-34-56.txt");
+        let vanished_path = dir
+            .join("VRChat")
+            .join("output_log_2024-01-30_12-34-56.txt");
+        let mut fs = FakeFileSystem::new();
+        fs.vanished = vec![vanished_path.clone()];
+        let fs = Arc::new(fs);
+        let mut unrotate = test_unrotate(Config::default(), &fs);
+        write_log(&*fs, &log_path, HEADER);
+        let stats = sweep(&mut unrotate);
+        assert_eq!(stats.scanned, 2);
+        assert_eq!(stats.linked, 1);
+        assert_eq!(stats.warnings.len(), 1);
+        assert_eq!(
+            outcome(&stats, &vanished_path),
+            Some(FileOutcome::Unreadable)
+        );
+        assert_eq!(outcome(&stats, &log_path), Some(FileOutcome::Linked));
+        let archived_path = stats
+            .files
+            .iter()
+            .find_map(|file| file.archived_path.clone());
+        assert_eq!(
+            fs.read(&archived_path.unwrap()).unwrap(),
+            fs.read(&log_path).unwrap()
+        );
+        // told only once
+        let stats = sweep(&mut unrotate);
+        assert!(stats.warnings.is_empty());
+        assert_eq!(outcome(&stats, &log_path), Some(FileOutcome::Unchanged));
+    }
+
+    #[test]
+    fn step_pauses_copying_while_the_drive_is_low_on_space() {
+        let dir = fake_dir();
+        let log_path = dir
+            .join("VRChat")
+            .join("output_log_2024-01-31_12-34-56.txt");
+        let fs = Arc::new(FakeFileSystem::new());
+        fs.free_space.store(1024, Ordering::SeqCst);
+        let mut unrotate = test_unrotate(Config::default(), &fs);
+        write_log(&*fs, &log_path, HEADER);
+        let stats = sweep(&mut unrotate);
+        assert_eq!(stats.linked, 0);
+        assert_eq!(stats.warnings.len(), 1);
+        assert!(stats.files.is_empty());
+        assert!(!fs.exists(&dir.join("collection")));
+        fs.free_space.store(u64::MAX, Ordering::SeqCst);
+        let stats = sweep(&mut unrotate);
+        assert_eq!(stats.linked, 1);
+        assert!(stats.warnings.is_empty());
+    }
+
+    #[test]
+    fn step_looks_at_a_live_log_again() {
+        let dir = fake_dir();
+        let log_path = dir
+            .join("VRChat")
+            .join("output_log_2024-01-31_12-34-56.txt");
+        let fs = Arc::new(FakeFileSystem::new());
+        fs.in_use.lock().unwrap().insert(log_path.clone());
+        let mut unrotate = test_unrotate(Config::default(), &fs);
+        write_log(&*fs, &log_path, HEADER);
+        let stats = sweep(&mut unrotate);
+        assert_eq!(outcome(&stats, &log_path), Some(FileOutcome::Linked));
+        let stats = sweep(&mut unrotate);
+        assert_eq!(
+            outcome(&stats, &log_path),
+            Some(FileOutcome::AlreadyArchived)
+        );
+    }
+
+    #[test]
+    fn dry_run_reports_a_log_it_would_move_as_linked() {
+        let dir = fake_dir();
+        let log_path = dir
+            .join("VRChat")
+            .join("output_log_2024-01-31_12-34-56.txt");
+        let fs = Arc::new(FakeFileSystem::new());
+        let mut unrotate = test_unrotate(Config::default(), &fs);
+        unrotate.collection.link_mode = LinkMode::Move;
+        unrotate.collection.dry_run = true;
+        write_log(&*fs, &log_path, HEADER);
+        let stats = sweep(&mut unrotate);
+        assert_eq!(stats.linked, 1);
+        assert_eq!(outcome(&stats, &log_path), Some(FileOutcome::Linked));
+        assert!(fs.exists(&log_path));
+        assert!(!fs.exists(&dir.join("collection")));
+    }
+
+    #[test]
+    fn archive_stores_logs_uncompressed_without_ntfs_compression() {
+        let dir = fake_dir();
+        let log_path = dir
+            .join("VRChat")
+            .join("output_log_2024-01-31_12-34-56.txt");
+        let mut fs = FakeFileSystem::new();
+        fs.no_compression = true;
+        let fs = Arc::new(fs);
+        let config = Config {
+            ntfs_compression: true,
+            ..Config::default()
+        };
+        let mut unrotate = test_unrotate(config, &fs);
+        write_log(&*fs, &log_path, HEADER);
+        let stats = sweep(&mut unrotate);
+        assert_eq!(stats.linked, 1);
+        assert_eq!(stats.warnings.len(), 1);
+        assert!(!unrotate.ntfs_compression);
+        let stats = sweep(&mut unrotate);
+        assert!(stats.warnings.is_empty());
+    }
+
+    #[test]
+    fn step_does_not_archive_a_renamed_log_again() {
+        let dir = fake_dir();
+        let log_path = dir.join("VRChat").join("output_log_12-34-56.txt");
+        let renamed_path = dir
+            .join("VRChat")
+            .join("output_log_2024-01-31_12-34-56.txt");
+        let fs = Arc::new(FakeFileSystem::new());
+        let mut unrotate = test_unrotate(Config::default(), &fs);
+        write_log(&*fs, &log_path, HEADER);
+        let stats = sweep(&mut unrotate);
+        assert_eq!(stats.linked, 1);
+        let archived_path = stats.files[0].archived_path.clone().unwrap();
+        fs.rename(&log_path, &renamed_path).unwrap();
+        let stats = sweep(&mut unrotate);
+        assert_eq!(stats.linked, 0);
+        assert_eq!(
+            outcome(&stats, &renamed_path),
+            Some(FileOutcome::AlreadyArchived)
+        );
+        assert_eq!(stats.files[0].archived_path, Some(archived_path.clone()));
+        let archived_logs = fs
+            .list_files(archived_path.parent().unwrap())
+            .unwrap()
+            .into_iter()
+            .filter(|path| path.extension() == Some("txt".as_ref()))
+            .count();
+        assert_eq!(archived_logs, 1);
+    }
+
+    #[test]
+    fn copy_verified_keeps_the_modified_time() {
+        let dir = TempDir::new("copy-verified");
+        let (from, to) = (dir.join("from.txt"), dir.join("to.txt"));
+        fs::write(&from, b"2024.01.31 12:34:56 Log\n").unwrap();
+        let modified = SystemTime::UNIX_EPOCH + Duration::from_secs(1_600_000_000);
+        set_modified(&from, modified);
+        copy_verified(&from, &to).unwrap();
+        assert_eq!(fs::read(&to).unwrap(), fs::read(&from).unwrap());
+        assert_eq!(fs::metadata(&to).unwrap().modified().unwrap(), modified);
+        let created = |path| fs::metadata(path).unwrap().created().unwrap();
+        assert_eq!(created(&to), created(&from));
+    }
 }
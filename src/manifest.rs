@@ -0,0 +1,80 @@
+use std::io;
+use std::path::{Path, PathBuf};
+
+use chrono::{DateTime, Local, NaiveDateTime};
+use serde::{Deserialize, Serialize};
+
+use crate::filesystem::FileSystem;
+
+/// Describes one archived logfile in a partition's `manifest.json`.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct ManifestEntry {
+    pub file_name: String,
+    pub original_path: PathBuf,
+    pub size: u64,
+    pub first_timestamp: NaiveDateTime,
+    pub archived_at: DateTime<Local>,
+}
+
+/// What `Manifest::upsert` did.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Upsert {
+    Unchanged,
+    Added,
+    /// Replaced the entry for the same file name, e.g. with the size of a grown log.
+    Updated,
+}
+
+/// Keeps a record of the partition's logfiles that survives renames and moves of the
+/// collection, with one entry per file name.
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct Manifest {
+    pub entries: Vec<ManifestEntry>,
+}
+
+impl Manifest {
+    pub fn path(partition_folder_path: &Path) -> PathBuf {
+        partition_folder_path.join("manifest.json")
+    }
+
+    pub fn load(fs: &dyn FileSystem, partition_folder_path: &Path) -> io::Result<Self> {
+        let text = match fs.read(&Self::path(partition_folder_path)) {
+            Ok(text) => text,
+            Err(e) => match e.kind() {
+                io::ErrorKind::NotFound => return Ok(Self::default()),
+                _ => return Err(e),
+            },
+        };
+        Ok(serde_json::from_slice(&text)?)
+    }
+
+    /// Replaces the entry for the same file name, keeping its `archived_at` if the file is unchanged.
+    pub fn upsert(&mut self, mut entry: ManifestEntry) -> Upsert {
+        match self
+            .entries
+            .iter_mut()
+            .find(|existing| existing.file_name == entry.file_name)
+        {
+            Some(existing) => {
+                entry.archived_at = existing.archived_at;
+                if *existing == entry {
+                    return Upsert::Unchanged;
+                }
+                *existing = entry;
+                Upsert::Updated
+            }
+            None => {
+                self.entries.push(entry);
+                Upsert::Added
+            }
+        }
+    }
+
+    pub fn save(&self, fs: &dyn FileSystem, partition_folder_path: &Path) -> io::Result<()> {
+        let path = Self::path(partition_folder_path);
+        // never leave a half-written manifest behind
+        let tmp_path = path.with_extension("json.tmp");
+        fs.write(&tmp_path, &serde_json::to_vec_pretty(self)?)?;
+        fs.rename(&tmp_path, &path)
+    }
+}
@@ -0,0 +1,69 @@
+use std::fs::File;
+use std::io;
+use std::ops::Deref;
+use std::os::windows::io::AsRawHandle;
+use std::ptr;
+use std::slice;
+
+use winapi::shared::minwindef::LPVOID;
+use winapi::um::{
+    handleapi::CloseHandle,
+    memoryapi::{CreateFileMappingW, MapViewOfFile, UnmapViewOfFile, FILE_MAP_READ},
+    winnt::PAGE_READONLY,
+};
+
+/// A read-only view of the beginning of a file.
+pub struct MappedPrefix {
+    ptr: LPVOID,
+    len: usize,
+}
+
+impl MappedPrefix {
+    /// Maps up to `max_len` bytes from the start of `file`.
+    /// Fails for an empty file, which cannot be mapped.
+    pub fn new(file: &File, max_len: usize) -> io::Result<Self> {
+        let file_len = file.metadata()?.len();
+        let len = file_len.min(max_len as u64) as usize;
+        if len == 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "cannot map an empty file",
+            ));
+        }
+        unsafe {
+            let mapping = CreateFileMappingW(
+                file.as_raw_handle(),
+                ptr::null_mut(),
+                PAGE_READONLY,
+                0,
+                0,
+                ptr::null(),
+            );
+            if mapping.is_null() {
+                return Err(io::Error::last_os_error());
+            }
+            let ptr = MapViewOfFile(mapping, FILE_MAP_READ, 0, 0, len);
+            // the view keeps the mapping alive on its own
+            let e = io::Error::last_os_error();
+            CloseHandle(mapping);
+            if ptr.is_null() {
+                return Err(e);
+            }
+            Ok(Self { ptr, len })
+        }
+    }
+}
+
+impl Deref for MappedPrefix {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        unsafe { slice::from_raw_parts(self.ptr as *const u8, self.len) }
+    }
+}
+
+impl Drop for MappedPrefix {
+    fn drop(&mut self) {
+        unsafe { UnmapViewOfFile(self.ptr) };
+    }
+}
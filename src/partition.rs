@@ -0,0 +1,100 @@
+use std::path::PathBuf;
+
+use anyhow::Result;
+use chrono::{Datelike, NaiveDate};
+use regex::Regex;
+
+/// The layout that has always been used, `2021-06/15`.
+pub const DEFAULT_TEMPLATE: &str = "{yyyy}-{MM}/{dd}";
+/// The default layout grouped by year, `2021/2021-06/15`, for archives spanning many years.
+pub const YEARLY_TEMPLATE: &str = "{yyyy}/{yyyy}-{MM}/{dd}";
+
+// each token with its group name and what it matches
+const TOKENS: [(&str, &str, &str); 3] = [
+    ("{yyyy}", "yyyy", "\\d{4}"),
+    ("{MM}", "MM", "\\d{2}"),
+    ("{dd}", "dd", "\\d{2}"),
+];
+
+/// Maps dates to partition folders relative to the collection and back,
+/// e.g. `{yyyy}/{MM}/{dd}` or `{yyyy}-{MM}-{dd}`. `/` separates folders, and a token may
+/// appear more than once, as in `YEARLY_TEMPLATE`.
+#[derive(Debug, Clone)]
+pub struct PartitionTemplate {
+    template: String,
+    re: Regex,
+}
+
+impl PartitionTemplate {
+    pub fn new(template: &str) -> Result<Self> {
+        let template = template.replace('\\', "/");
+        for (token, _, _) in &TOKENS {
+            if !template.contains(token) {
+                anyhow::bail!("partition template must contain {}", token);
+            }
+        }
+        let mut literal = template.clone();
+        for (token, _, _) in &TOKENS {
+            literal = literal.replace(token, "0");
+        }
+        for component in literal.split('/') {
+            if component.is_empty() || component == "." || component == ".." {
+                anyhow::bail!("partition template must be a relative path: {}", template);
+            }
+            // characters Windows does not allow in file names, which also rules out drive letters
+            if let Some(c) = component
+                .chars()
+                .find(|&c| "<>:\"|?*{}".contains(c) || c.is_control())
+            {
+                anyhow::bail!("partition template must not contain {:?}", c);
+            }
+        }
+        // only the first occurrence is captured, `parse` checks that the others agree
+        let mut pattern = regex::escape(&template);
+        for (token, name, digits) in &TOKENS {
+            let token = regex::escape(token);
+            pattern = pattern.replacen(&token, &format!("(?P<{}>{})", name, digits), 1);
+            pattern = pattern.replace(&token, digits);
+        }
+        let re = Regex::new(&format!("^{}$", pattern)).unwrap();
+        Ok(Self { template, re })
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.template
+    }
+
+    /// The number of nested folders a partition consists of.
+    pub fn depth(&self) -> usize {
+        self.template.split('/').count()
+    }
+
+    pub fn render(&self, date: NaiveDate) -> PathBuf {
+        let rendered = self
+            .template
+            .replace("{yyyy}", &format!("{:04}", date.year()))
+            .replace("{MM}", &format!("{:02}", date.month()))
+            .replace("{dd}", &format!("{:02}", date.day()));
+        rendered.split('/').collect()
+    }
+
+    /// Takes a path relative to the collection, with `/` as the separator.
+    pub fn parse(&self, relative_path: &str) -> Option<NaiveDate> {
+        let captures = self.re.captures(relative_path)?;
+        let date = NaiveDate::from_ymd_opt(
+            captures["yyyy"].parse().ok()?,
+            captures["MM"].parse().ok()?,
+            captures["dd"].parse().ok()?,
+        )?;
+        // e.g. `2021/2022-06/15` with `YEARLY_TEMPLATE`
+        let rendered = self.render(date);
+        let rendered = rendered.iter().map(|c| c.to_string_lossy());
+        rendered.eq(relative_path.split('/')).then_some(date)
+    }
+}
+
+impl Default for PartitionTemplate {
+    fn default() -> Self {
+        Self::new(DEFAULT_TEMPLATE).unwrap()
+    }
+}
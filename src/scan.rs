@@ -0,0 +1,170 @@
+use std::io::{self, Read};
+use std::ops::ControlFlow;
+
+/// How much of a log is held in memory at once, and so the longest line seen whole.
+const WINDOW_LEN: usize = 64 * 1024;
+
+/// Hands each line of `reader`, newline included, to `f` until it breaks, reading at most
+/// `max_bytes` if given. Memory stays bounded by `WINDOW_LEN` however large the log is:
+/// a longer line is only seen up to that length.
+pub fn for_each_line(
+    reader: impl Read,
+    max_bytes: Option<u64>,
+    mut f: impl FnMut(&[u8]) -> ControlFlow<()>,
+) -> io::Result<()> {
+    let mut reader = reader.take(max_bytes.unwrap_or(u64::MAX));
+    let mut window = vec![0; WINDOW_LEN];
+    let mut filled = 0;
+    // set while passing over the rest of a line that did not fit
+    let mut skipping = false;
+    loop {
+        let len = match reader.read(&mut window[filled..]) {
+            Ok(len) => len,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        };
+        filled += len;
+        let mut start = 0;
+        while let Some(newline) = window[start..filled].iter().position(|&b| b == b'\n') {
+            let end = start + newline + 1;
+            if !skipping && f(&window[start..end]).is_break() {
+                return Ok(());
+            }
+            skipping = false;
+            start = end;
+        }
+        if len == 0 {
+            // the last line may lack a newline
+            if start < filled && !skipping {
+                let _ = f(&window[start..filled]);
+            }
+            return Ok(());
+        }
+        if start == 0 && filled == WINDOW_LEN {
+            if !skipping && f(&window[..]).is_break() {
+                return Ok(());
+            }
+            skipping = true;
+            filled = 0;
+            continue;
+        }
+        window.copy_within(start..filled, 0);
+        filled -= start;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lines(data: &[u8], max_bytes: Option<u64>) -> Vec<Vec<u8>> {
+        let mut lines = vec![];
+        for_each_line(data, max_bytes, |line| {
+            lines.push(line.to_vec());
+            ControlFlow::Continue(())
+        })
+        .unwrap();
+        lines
+    }
+
+    fn filler(len: usize) -> Vec<u8> {
+        let mut data = vec![b'x'; len];
+        for i in (99..len).step_by(100) {
+            data[i] = b'\n';
+        }
+        data
+    }
+
+    #[test]
+    fn sees_a_line_across_the_window_boundary_whole() {
+        let mut data = filler(WINDOW_LEN - 10);
+        data.push(b'\n');
+        data.extend_from_slice(b"2024.01.31 12:34:56 Log - [Behaviour] Entering Room: Home\n");
+        data.extend_from_slice(&filler(1000));
+        let lines = lines(&data, None);
+        assert!(lines
+            .iter()
+            .any(|line| line == b"2024.01.31 12:34:56 Log - [Behaviour] Entering Room: Home\n"));
+        assert_eq!(lines.concat(), data);
+    }
+
+    #[test]
+    fn stops_at_max_bytes() {
+        let data = filler(10_000);
+        let seen: usize = lines(&data, Some(1234)).iter().map(Vec::len).sum();
+        assert_eq!(seen, 1234);
+    }
+
+    #[test]
+    fn ignores_a_match_past_max_bytes() {
+        let mut data = filler(5000);
+        data.push(b'\n');
+        data.extend_from_slice(b"needle\n");
+        let found = |max_bytes| {
+            lines(&data, max_bytes)
+                .iter()
+                .any(|line| line == b"needle\n")
+        };
+        assert!(found(None));
+        assert!(!found(Some(5000)));
+    }
+
+    /// Counts the bytes taken from the reader it wraps.
+    struct Counting<'a> {
+        inner: &'a [u8],
+        read: u64,
+    }
+
+    impl Read for Counting<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            let len = self.inner.read(buf)?;
+            self.read += len as u64;
+            Ok(len)
+        }
+    }
+
+    #[test]
+    fn finds_a_line_many_windows_into_a_large_log() {
+        let needle = b"2024.01.31 12:34:56 Log - [Behaviour] Entering Room: Home\n";
+        let mut data = filler(3 * 1024 * 1024);
+        data.push(b'\n');
+        let offset = data.len() as u64;
+        data.extend_from_slice(needle);
+        data.extend_from_slice(&filler(1024 * 1024));
+        assert!(offset > 40 * WINDOW_LEN as u64);
+
+        let scan = |max_bytes| {
+            let mut reader = Counting {
+                inner: &data,
+                read: 0,
+            };
+            let mut found = false;
+            for_each_line(&mut reader, max_bytes, |line| {
+                found |= line == needle;
+                ControlFlow::Continue(())
+            })
+            .unwrap();
+            (found, reader.read)
+        };
+        assert_eq!(scan(None), (true, data.len() as u64));
+        assert_eq!(scan(Some(offset + 2048)), (true, offset + 2048));
+        assert_eq!(scan(Some(offset)), (false, offset));
+        assert_eq!(scan(Some(1024 * 1024)), (false, 1024 * 1024));
+    }
+
+    #[test]
+    fn stops_once_broken() {
+        let data = b"one\ntwo\nthree\n";
+        let mut seen = vec![];
+        for_each_line(&data[..], None, |line| {
+            seen.push(line.to_vec());
+            if line == b"two\n" {
+                ControlFlow::Break(())
+            } else {
+                ControlFlow::Continue(())
+            }
+        })
+        .unwrap();
+        assert_eq!(seen, [b"one\n".to_vec(), b"two\n".to_vec()]);
+    }
+}
@@ -0,0 +1,235 @@
+use std::ffi::CStr;
+use std::io;
+use std::os::raw::{c_char, c_int};
+use std::path::Path;
+use std::ptr;
+
+/// Just as much of SQLite as `ArchiveIndex` needs, from the `winsqlite3.dll` that comes
+/// with Windows 10 and later.
+mod ffi {
+    use std::os::raw::{c_char, c_int, c_void};
+
+    #[repr(C)]
+    pub struct Sqlite3 {
+        _private: [u8; 0],
+    }
+
+    #[repr(C)]
+    pub struct Sqlite3Stmt {
+        _private: [u8; 0],
+    }
+
+    pub const SQLITE_OK: c_int = 0;
+    pub const SQLITE_CORRUPT: c_int = 11;
+    pub const SQLITE_NOTADB: c_int = 26;
+    pub const SQLITE_ROW: c_int = 100;
+    pub const SQLITE_DONE: c_int = 101;
+    pub const SQLITE_OPEN_READWRITE: c_int = 0x02;
+    pub const SQLITE_OPEN_CREATE: c_int = 0x04;
+    /// Has SQLite copy a bound value, which then need not outlive the statement.
+    pub const SQLITE_TRANSIENT: isize = -1;
+
+    #[link(name = "winsqlite3", kind = "raw-dylib")]
+    extern "system" {
+        pub fn sqlite3_open_v2(
+            filename: *const c_char,
+            db: *mut *mut Sqlite3,
+            flags: c_int,
+            vfs: *const c_char,
+        ) -> c_int;
+        pub fn sqlite3_close(db: *mut Sqlite3) -> c_int;
+        pub fn sqlite3_errmsg(db: *mut Sqlite3) -> *const c_char;
+        pub fn sqlite3_busy_timeout(db: *mut Sqlite3, ms: c_int) -> c_int;
+        pub fn sqlite3_exec(
+            db: *mut Sqlite3,
+            sql: *const c_char,
+            callback: *mut c_void,
+            arg: *mut c_void,
+            errmsg: *mut *mut c_char,
+        ) -> c_int;
+        pub fn sqlite3_prepare_v2(
+            db: *mut Sqlite3,
+            sql: *const c_char,
+            len: c_int,
+            stmt: *mut *mut Sqlite3Stmt,
+            tail: *mut *const c_char,
+        ) -> c_int;
+        pub fn sqlite3_reset(stmt: *mut Sqlite3Stmt) -> c_int;
+        pub fn sqlite3_bind_text(
+            stmt: *mut Sqlite3Stmt,
+            index: c_int,
+            text: *const c_char,
+            len: c_int,
+            destructor: isize,
+        ) -> c_int;
+        pub fn sqlite3_bind_int64(stmt: *mut Sqlite3Stmt, index: c_int, value: i64) -> c_int;
+        pub fn sqlite3_step(stmt: *mut Sqlite3Stmt) -> c_int;
+        pub fn sqlite3_column_text(stmt: *mut Sqlite3Stmt, column: c_int) -> *const u8;
+        pub fn sqlite3_column_bytes(stmt: *mut Sqlite3Stmt, column: c_int) -> c_int;
+        pub fn sqlite3_column_int64(stmt: *mut Sqlite3Stmt, column: c_int) -> i64;
+        pub fn sqlite3_finalize(stmt: *mut Sqlite3Stmt) -> c_int;
+    }
+}
+
+/// A value bound to a `?` of a statement.
+pub enum Value<'a> {
+    Integer(i64),
+    Text(&'a str),
+}
+
+/// An open database file, closed on drop.
+pub struct Connection {
+    db: *mut ffi::Sqlite3,
+}
+
+impl Connection {
+    /// Opens the database at `path`, creating an empty one if there is none.
+    pub fn open(path: &Path) -> io::Result<Self> {
+        let path = path
+            .to_str()
+            .and_then(|path| std::ffi::CString::new(path).ok())
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "unsupported path"))?;
+        let mut db = ptr::null_mut();
+        let code = unsafe {
+            ffi::sqlite3_open_v2(
+                path.as_ptr(),
+                &mut db,
+                ffi::SQLITE_OPEN_READWRITE | ffi::SQLITE_OPEN_CREATE,
+                ptr::null(),
+            )
+        };
+        // a handle comes back even on failure, to tell what went wrong
+        let connection = Self { db };
+        connection.check(code)?;
+        // wait out another instance writing to it, e.g. `--once` next to the tray app
+        unsafe { ffi::sqlite3_busy_timeout(db, 5000) };
+        Ok(connection)
+    }
+
+    /// Runs one or more statements that take no values.
+    pub fn execute_batch(&self, sql: &str) -> io::Result<()> {
+        let sql = std::ffi::CString::new(sql)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+        self.check(unsafe {
+            ffi::sqlite3_exec(
+                self.db,
+                sql.as_ptr(),
+                ptr::null_mut(),
+                ptr::null_mut(),
+                ptr::null_mut(),
+            )
+        })
+    }
+
+    pub fn prepare(&self, sql: &str) -> io::Result<Statement<'_>> {
+        let mut stmt = ptr::null_mut();
+        self.check(unsafe {
+            ffi::sqlite3_prepare_v2(
+                self.db,
+                sql.as_ptr() as *const c_char,
+                sql.len() as c_int,
+                &mut stmt,
+                ptr::null_mut(),
+            )
+        })?;
+        Ok(Statement {
+            connection: self,
+            stmt,
+        })
+    }
+
+    /// Turns a result code into an error with SQLite's message. A damaged database gives
+    /// `InvalidData`, so that it can be told apart and built anew.
+    fn check(&self, code: c_int) -> io::Result<()> {
+        if matches!(code, ffi::SQLITE_OK | ffi::SQLITE_ROW | ffi::SQLITE_DONE) {
+            return Ok(());
+        }
+        let message = if self.db.is_null() {
+            format!("SQLite error {}", code)
+        } else {
+            unsafe { CStr::from_ptr(ffi::sqlite3_errmsg(self.db)) }
+                .to_string_lossy()
+                .into_owned()
+        };
+        // the extended codes keep the primary one in the low byte
+        let kind = match code & 0xff {
+            ffi::SQLITE_CORRUPT | ffi::SQLITE_NOTADB => io::ErrorKind::InvalidData,
+            _ => io::ErrorKind::Other,
+        };
+        Err(io::Error::new(kind, message))
+    }
+}
+
+impl Drop for Connection {
+    fn drop(&mut self) {
+        unsafe { ffi::sqlite3_close(self.db) };
+    }
+}
+
+/// A prepared statement, finalized on drop.
+pub struct Statement<'c> {
+    connection: &'c Connection,
+    stmt: *mut ffi::Sqlite3Stmt,
+}
+
+impl Statement<'_> {
+    /// Starts the statement over with `values` for its `?`s, in order.
+    pub fn bind(&mut self, values: &[Value]) -> io::Result<()> {
+        unsafe { ffi::sqlite3_reset(self.stmt) };
+        for (index, value) in values.iter().enumerate() {
+            let index = index as c_int + 1;
+            let code = match *value {
+                Value::Integer(value) => unsafe {
+                    ffi::sqlite3_bind_int64(self.stmt, index, value)
+                },
+                Value::Text(text) => unsafe {
+                    ffi::sqlite3_bind_text(
+                        self.stmt,
+                        index,
+                        text.as_ptr() as *const c_char,
+                        text.len() as c_int,
+                        ffi::SQLITE_TRANSIENT,
+                    )
+                },
+            };
+            self.connection.check(code)?;
+        }
+        Ok(())
+    }
+
+    /// Runs the statement with `values` to the end, for one that returns no rows.
+    pub fn execute(&mut self, values: &[Value]) -> io::Result<()> {
+        self.bind(values)?;
+        while self.step()? {}
+        Ok(())
+    }
+
+    /// Moves on to the next row, returning whether there was one.
+    pub fn step(&mut self) -> io::Result<bool> {
+        let code = unsafe { ffi::sqlite3_step(self.stmt) };
+        self.connection.check(code)?;
+        Ok(code == ffi::SQLITE_ROW)
+    }
+
+    pub fn integer(&self, column: usize) -> i64 {
+        unsafe { ffi::sqlite3_column_int64(self.stmt, column as c_int) }
+    }
+
+    pub fn text(&self, column: usize) -> String {
+        unsafe {
+            // the length is only known once the text has been asked for
+            let text = ffi::sqlite3_column_text(self.stmt, column as c_int);
+            if text.is_null() {
+                return String::new();
+            }
+            let len = ffi::sqlite3_column_bytes(self.stmt, column as c_int) as usize;
+            String::from_utf8_lossy(std::slice::from_raw_parts(text, len)).into_owned()
+        }
+    }
+}
+
+impl Drop for Statement<'_> {
+    fn drop(&mut self) {
+        unsafe { ffi::sqlite3_finalize(self.stmt) };
+    }
+}
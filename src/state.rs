@@ -0,0 +1,69 @@
+use std::collections::HashMap;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use chrono::{DateTime, Local};
+use serde::{Deserialize, Serialize};
+
+use crate::filesystem::FileSystem;
+use crate::LogIdentity;
+
+/// Bumped whenever the format changes; a state of any other version is ignored.
+const VERSION: u32 = 1;
+
+/// What the worker knows about the collection, so that a restart does not have to
+/// look at every archived log again.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct State {
+    version: u32,
+    /// The state is only valid for the collection it was written for.
+    pub collection_path: PathBuf,
+    pub last_scan: Option<DateTime<Local>>,
+    pub processed: HashMap<PathBuf, SystemTime>,
+    pub archived_names: Vec<(LogIdentity, String)>,
+    /// When each error was last notified, keyed on its message.
+    #[serde(default)]
+    pub notified: HashMap<String, DateTime<Local>>,
+}
+
+#[derive(Deserialize)]
+struct Versioned {
+    version: u32,
+}
+
+impl State {
+    pub fn new(collection_path: PathBuf) -> Self {
+        Self {
+            version: VERSION,
+            collection_path,
+            last_scan: None,
+            processed: HashMap::new(),
+            archived_names: vec![],
+            notified: HashMap::new(),
+        }
+    }
+
+    pub fn path(locallow_path: &Path) -> PathBuf {
+        crate::app_data_path(locallow_path).join("state.json")
+    }
+
+    /// Returns `None` if the state is missing, corrupt or of another version,
+    /// in which case the caller rebuilds it from the collection.
+    pub fn load(fs: &dyn FileSystem, state_path: &Path) -> Option<Self> {
+        let text = fs.read(state_path).ok()?;
+        let versioned: Versioned = serde_json::from_slice(&text).ok()?;
+        if versioned.version != VERSION {
+            return None;
+        }
+        serde_json::from_slice(&text).ok()
+    }
+
+    pub fn save(&self, fs: &dyn FileSystem, state_path: &Path) -> io::Result<()> {
+        fs.create_dir_all(state_path.parent().unwrap())?;
+        // a crash while writing must not leave a truncated state behind
+        let tmp_path = state_path.with_extension("json.tmp");
+        fs.write(&tmp_path, &serde_json::to_vec(self)?)?;
+        fs.rename(&tmp_path, state_path)
+    }
+}
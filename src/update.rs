@@ -0,0 +1,158 @@
+use std::ffi::OsStr;
+use std::fs;
+use std::io;
+use std::iter;
+use std::os::windows::ffi::OsStrExt;
+use std::path::Path;
+use std::ptr;
+
+use chrono::{Local, NaiveDate};
+use serde::Deserialize;
+use winapi::shared::minwindef::DWORD;
+use winapi::um::winhttp::{
+    WinHttpCloseHandle, WinHttpConnect, WinHttpOpen, WinHttpOpenRequest, WinHttpQueryHeaders,
+    WinHttpReadData, WinHttpReceiveResponse, WinHttpSendRequest, HINTERNET,
+    INTERNET_DEFAULT_HTTPS_PORT, WINHTTP_ACCESS_TYPE_AUTOMATIC_PROXY, WINHTTP_FLAG_SECURE,
+    WINHTTP_QUERY_FLAG_NUMBER, WINHTTP_QUERY_STATUS_CODE,
+};
+
+use crate::APP_NAME;
+
+pub const RELEASES_PAGE: &str = "https://github.com/KOBA789/vrclogunrotate/releases";
+const API_HOST: &str = "api.github.com";
+const LATEST_RELEASE_PATH: &str = "/repos/KOBA789/vrclogunrotate/releases/latest";
+
+fn wide(s: &str) -> Vec<u16> {
+    OsStr::new(s).encode_wide().chain(iter::once(0)).collect()
+}
+
+struct Handle(HINTERNET);
+
+impl Handle {
+    fn new(handle: HINTERNET) -> io::Result<Self> {
+        if handle.is_null() {
+            Err(io::Error::last_os_error())
+        } else {
+            Ok(Self(handle))
+        }
+    }
+}
+
+impl Drop for Handle {
+    fn drop(&mut self) {
+        unsafe { WinHttpCloseHandle(self.0) };
+    }
+}
+
+fn check(ret: i32) -> io::Result<()> {
+    if ret == 0 {
+        Err(io::Error::last_os_error())
+    } else {
+        Ok(())
+    }
+}
+
+/// Fetches `https://<host><path>` with the system proxy settings.
+fn https_get(host: &str, path: &str) -> io::Result<Vec<u8>> {
+    unsafe {
+        // GitHub rejects requests without a user agent
+        let session = Handle::new(WinHttpOpen(
+            wide(APP_NAME).as_ptr(),
+            WINHTTP_ACCESS_TYPE_AUTOMATIC_PROXY,
+            ptr::null(),
+            ptr::null(),
+            0,
+        ))?;
+        let connection = Handle::new(WinHttpConnect(
+            session.0,
+            wide(host).as_ptr(),
+            INTERNET_DEFAULT_HTTPS_PORT,
+            0,
+        ))?;
+        let request = Handle::new(WinHttpOpenRequest(
+            connection.0,
+            wide("GET").as_ptr(),
+            wide(path).as_ptr(),
+            ptr::null(),
+            ptr::null(),
+            ptr::null_mut(),
+            WINHTTP_FLAG_SECURE,
+        ))?;
+        check(WinHttpSendRequest(
+            request.0,
+            ptr::null(),
+            0,
+            ptr::null_mut(),
+            0,
+            0,
+            0,
+        ))?;
+        check(WinHttpReceiveResponse(request.0, ptr::null_mut()))?;
+        let mut status: DWORD = 0;
+        let mut status_len = std::mem::size_of::<DWORD>() as DWORD;
+        check(WinHttpQueryHeaders(
+            request.0,
+            WINHTTP_QUERY_STATUS_CODE | WINHTTP_QUERY_FLAG_NUMBER,
+            ptr::null(),
+            &mut status as *mut DWORD as *mut _,
+            &mut status_len,
+            ptr::null_mut(),
+        ))?;
+        if status != 200 {
+            return Err(io::Error::other(format!("HTTP status {}", status)));
+        }
+        let mut body = vec![];
+        let mut buf = [0u8; 8192];
+        loop {
+            let mut read = 0;
+            check(WinHttpReadData(
+                request.0,
+                buf.as_mut_ptr() as *mut _,
+                buf.len() as DWORD,
+                &mut read,
+            ))?;
+            if read == 0 {
+                return Ok(body);
+            }
+            body.extend_from_slice(&buf[..read as usize]);
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct Release {
+    tag_name: String,
+}
+
+pub fn latest_release_tag() -> io::Result<String> {
+    let body = https_get(API_HOST, LATEST_RELEASE_PATH)?;
+    let release: Release = serde_json::from_slice(&body)?;
+    Ok(release.tag_name)
+}
+
+/// Compares dotted version numbers, ignoring a leading `v` and anything after a `-`.
+pub fn is_newer(tag: &str, current: &str) -> bool {
+    fn parse(version: &str) -> Vec<u64> {
+        let version = version.trim_start_matches('v');
+        let version = version.split('-').next().unwrap_or_default();
+        version
+            .split('.')
+            .map(|part| part.parse().unwrap_or(0))
+            .collect()
+    }
+    parse(tag) > parse(current)
+}
+
+/// Returns `true` and records today's check in `stamp_path` unless it already happened today.
+pub fn claim_daily_check(stamp_path: &Path) -> bool {
+    let today = Local::now().naive_local().date();
+    let last_check = fs::read_to_string(stamp_path)
+        .ok()
+        .and_then(|text| text.trim().parse::<NaiveDate>().ok());
+    if last_check == Some(today) {
+        return false;
+    }
+    let _ = fs::create_dir_all(stamp_path.parent().unwrap());
+    let _ = fs::write(stamp_path, today.to_string());
+    true
+}
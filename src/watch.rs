@@ -0,0 +1,257 @@
+use std::ffi::c_void;
+use std::io;
+use std::iter;
+use std::mem;
+use std::os::windows::ffi::OsStrExt;
+use std::path::Path;
+use std::ptr;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use winapi::shared::minwindef::{DWORD, FALSE, TRUE};
+use winapi::shared::winerror::WAIT_TIMEOUT;
+use winapi::um::{
+    fileapi::{CreateFileW, OPEN_EXISTING},
+    handleapi::{CloseHandle, INVALID_HANDLE_VALUE},
+    ioapiset::{CancelIoEx, GetOverlappedResult},
+    minwinbase::OVERLAPPED,
+    synchapi::{CreateEventW, SetEvent, WaitForMultipleObjects, WaitForSingleObject},
+    winbase::{
+        ReadDirectoryChangesW, FILE_FLAG_BACKUP_SEMANTICS, FILE_FLAG_OVERLAPPED, INFINITE,
+        WAIT_OBJECT_0,
+    },
+    winnt::{
+        FILE_LIST_DIRECTORY, FILE_NOTIFY_CHANGE_FILE_NAME, FILE_NOTIFY_CHANGE_LAST_WRITE,
+        FILE_NOTIFY_CHANGE_SIZE, FILE_SHARE_DELETE, FILE_SHARE_READ, FILE_SHARE_WRITE, HANDLE,
+        MAXIMUM_WAIT_OBJECTS,
+    },
+};
+
+const NOTIFY_FILTER: DWORD =
+    FILE_NOTIFY_CHANGE_FILE_NAME | FILE_NOTIFY_CHANGE_SIZE | FILE_NOTIFY_CHANGE_LAST_WRITE;
+
+// a burst of changes (e.g. VRChat flushing its log) is gathered into a single wakeup
+const COALESCE_QUIET: Duration = Duration::from_millis(200);
+const COALESCE_MAX: Duration = Duration::from_secs(1);
+
+fn timeout_millis(timeout: Duration) -> DWORD {
+    timeout.as_millis().min((INFINITE - 1) as u128) as DWORD
+}
+
+/// Lets another thread cut a wait of the worker short.
+pub struct Waker {
+    // auto-reset, so each wake is consumed by exactly one wait
+    event: HANDLE,
+}
+
+// an event handle may be signaled and waited on from any thread
+unsafe impl Send for Waker {}
+unsafe impl Sync for Waker {}
+
+impl Waker {
+    pub fn new() -> io::Result<Self> {
+        let event = unsafe { CreateEventW(ptr::null_mut(), FALSE, FALSE, ptr::null()) };
+        if event.is_null() {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(Self { event })
+    }
+
+    pub fn wake(&self) {
+        unsafe { SetEvent(self.event) };
+    }
+
+    /// Sleeps until woken or `timeout` elapses. Returns `true` if woken.
+    pub fn wait(&self, timeout: Duration) -> bool {
+        match unsafe { WaitForSingleObject(self.event, timeout_millis(timeout)) } {
+            WAIT_OBJECT_0 => true,
+            WAIT_TIMEOUT => false,
+            _ => {
+                // still honor the timeout rather than spinning
+                thread::sleep(timeout);
+                false
+            }
+        }
+    }
+}
+
+impl Drop for Waker {
+    fn drop(&mut self) {
+        unsafe { CloseHandle(self.event) };
+    }
+}
+
+/// Why `DirectoryWatcher::wait` returned.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Wakeup {
+    Changed,
+    Woken,
+    TimedOut,
+}
+
+pub struct DirectoryWatcher {
+    directory: HANDLE,
+    overlapped: Box<OVERLAPPED>,
+    // ReadDirectoryChangesW requires a DWORD-aligned buffer
+    buf: Vec<DWORD>,
+    pending: bool,
+}
+
+// the handles are only ever touched by the thread owning the watcher
+unsafe impl Send for DirectoryWatcher {}
+
+impl DirectoryWatcher {
+    pub fn new(path: &Path) -> io::Result<Self> {
+        let path: Vec<_> = path
+            .as_os_str()
+            .encode_wide()
+            .chain(iter::once(0))
+            .collect();
+        let directory = unsafe {
+            CreateFileW(
+                path.as_ptr(),
+                FILE_LIST_DIRECTORY,
+                FILE_SHARE_READ | FILE_SHARE_WRITE | FILE_SHARE_DELETE,
+                ptr::null_mut(),
+                OPEN_EXISTING,
+                FILE_FLAG_BACKUP_SEMANTICS | FILE_FLAG_OVERLAPPED,
+                ptr::null_mut(),
+            )
+        };
+        if directory == INVALID_HANDLE_VALUE {
+            return Err(io::Error::last_os_error());
+        }
+        let event = unsafe { CreateEventW(ptr::null_mut(), TRUE, FALSE, ptr::null()) };
+        if event.is_null() {
+            let e = io::Error::last_os_error();
+            unsafe { CloseHandle(directory) };
+            return Err(e);
+        }
+        let mut overlapped: Box<OVERLAPPED> = Box::new(unsafe { mem::zeroed() });
+        overlapped.hEvent = event;
+        let mut watcher = Self {
+            directory,
+            overlapped,
+            buf: vec![0; 1024],
+            pending: false,
+        };
+        watcher.read_changes()?;
+        Ok(watcher)
+    }
+
+    fn read_changes(&mut self) -> io::Result<()> {
+        let ret = unsafe {
+            ReadDirectoryChangesW(
+                self.directory,
+                self.buf.as_mut_ptr() as *mut c_void,
+                (self.buf.len() * mem::size_of::<DWORD>()) as DWORD,
+                FALSE,
+                NOTIFY_FILTER,
+                ptr::null_mut(),
+                &mut *self.overlapped,
+                None,
+            )
+        };
+        if ret == 0 {
+            return Err(io::Error::last_os_error());
+        }
+        self.pending = true;
+        Ok(())
+    }
+
+    /// Takes the changes that were signaled, and starts reading the next ones.
+    fn complete(&mut self) -> io::Result<()> {
+        let mut transferred = 0;
+        let ret = unsafe {
+            GetOverlappedResult(
+                self.directory,
+                &mut *self.overlapped,
+                &mut transferred,
+                FALSE,
+            )
+        };
+        self.pending = false;
+        if ret == 0 {
+            return Err(io::Error::last_os_error());
+        }
+        // the notifications themselves are not needed since the caller rescans anyway
+        self.read_changes()
+    }
+}
+
+/// Blocks until something in one of the directories of `watchers` changes, `waker` is
+/// woken or `timeout` elapses.
+pub fn wait(
+    watchers: &mut [DirectoryWatcher],
+    timeout: Duration,
+    waker: &Waker,
+) -> io::Result<Wakeup> {
+    // one slot is taken by the waker
+    let count = watchers.len().min(MAXIMUM_WAIT_OBJECTS as usize - 1);
+    let watchers = &mut watchers[..count];
+    let handles: Vec<HANDLE> = watchers
+        .iter()
+        .map(|watcher| watcher.overlapped.hEvent)
+        .chain(iter::once(waker.event))
+        .collect();
+    let ret = unsafe {
+        WaitForMultipleObjects(
+            handles.len() as DWORD,
+            handles.as_ptr(),
+            FALSE,
+            timeout_millis(timeout),
+        )
+    };
+    match ret.wrapping_sub(WAIT_OBJECT_0) as usize {
+        changed if changed < watchers.len() => {
+            watchers[changed].complete()?;
+            Ok(Wakeup::Changed)
+        }
+        woken if woken == watchers.len() => Ok(Wakeup::Woken),
+        _ if ret == WAIT_TIMEOUT => Ok(Wakeup::TimedOut),
+        _ => Err(io::Error::last_os_error()),
+    }
+}
+
+/// Like `wait`, but after the first change keeps absorbing follow-up changes
+/// for a short while so that a burst of writes results in a single wakeup.
+pub fn wait_coalesced(
+    watchers: &mut [DirectoryWatcher],
+    timeout: Duration,
+    waker: &Waker,
+) -> io::Result<Wakeup> {
+    let wakeup = wait(watchers, timeout, waker)?;
+    if wakeup != Wakeup::Changed {
+        return Ok(wakeup);
+    }
+    let deadline = Instant::now() + COALESCE_MAX;
+    while let Some(remaining) = deadline.checked_duration_since(Instant::now()) {
+        match wait(watchers, remaining.min(COALESCE_QUIET), waker)? {
+            Wakeup::Changed => {}
+            // a manual scan should not have to wait for VRChat to settle
+            Wakeup::Woken => return Ok(Wakeup::Woken),
+            Wakeup::TimedOut => break,
+        }
+    }
+    Ok(Wakeup::Changed)
+}
+
+impl Drop for DirectoryWatcher {
+    fn drop(&mut self) {
+        unsafe {
+            if self.pending {
+                // the kernel must be done with the buffer before it is freed
+                CancelIoEx(self.directory, &mut *self.overlapped);
+                let mut transferred = 0;
+                GetOverlappedResult(
+                    self.directory,
+                    &mut *self.overlapped,
+                    &mut transferred,
+                    TRUE,
+                );
+            }
+            CloseHandle(self.overlapped.hEvent);
+            CloseHandle(self.directory);
+        }
+    }
+}
@@ -0,0 +1,417 @@
+use std::ops::ControlFlow;
+use std::panic::{self, AssertUnwindSafe};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{mpsc, Arc, Condvar, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use chrono::Local;
+
+use crate::config::Config;
+use crate::diagnostics::Diagnostics;
+use crate::watch::{self, Waker, Wakeup};
+use crate::{
+    backoff, panic_message, process, NotificationLevel, StepReport, Unrotate, WorkerStatus,
+    VRCHAT_EXE,
+};
+
+/// How often the supervisor looks at the worker.
+const SUPERVISE_INTERVAL: Duration = Duration::from_secs(30);
+/// How long a single step may take before the worker counts as stuck.
+const STEP_TIMEOUT: Duration = Duration::from_secs(30 * 60);
+/// How far past a planned wakeup the worker may be before it counts as stuck.
+const MIN_WAKE_GRACE: Duration = Duration::from_secs(5 * 60);
+/// Consecutive restarts without a successful step before giving up.
+const MAX_RESTARTS: u32 = 3;
+/// The fewest new logs in one step that get their progress shown.
+const MIN_BACKLOG: usize = 20;
+/// How often the progress of a backlog is sent to the UI at most.
+const PROGRESS_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Everything the worker shares with the UI, kept so that a replacement worker can
+/// pick up where a dead one left off.
+pub struct WorkerContext {
+    pub locallow: PathBuf,
+    /// The settings as last applied, for starting a replacement worker.
+    pub config: Mutex<Config>,
+    pub config_rx: Mutex<mpsc::Receiver<Config>>,
+    pub error_tx: mpsc::SyncSender<(anyhow::Error, NotificationLevel)>,
+    pub error_notifier: nwg::NoticeSender,
+    pub status_tx: mpsc::Sender<WorkerStatus>,
+    pub status_notifier: nwg::NoticeSender,
+    pub crash_notifier: nwg::NoticeSender,
+    pub paused: Arc<AtomicBool>,
+    pub waker: Arc<Waker>,
+    pub diagnostics: Diagnostics,
+    // seconds since the epoch by which the worker has to check in again
+    deadline: AtomicU64,
+    // successful steps so far, to tell a recovered worker from one that keeps dying
+    steps: AtomicU64,
+    // bumped to retire a stuck worker, should it ever wake up
+    generation: AtomicU64,
+    // set on exit, after which the worker stops at the next safe point
+    shutdown: AtomicBool,
+    // whether a step is under way, guarded together with `shutdown`
+    busy: Mutex<bool>,
+    idle: Condvar,
+}
+
+enum Exit {
+    /// The UI has gone away.
+    Shutdown,
+    /// A replacement worker has taken over.
+    Superseded,
+}
+
+fn now_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
+
+impl WorkerContext {
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        locallow: PathBuf,
+        config: Config,
+        config_rx: mpsc::Receiver<Config>,
+        error_tx: mpsc::SyncSender<(anyhow::Error, NotificationLevel)>,
+        error_notifier: nwg::NoticeSender,
+        status_tx: mpsc::Sender<WorkerStatus>,
+        status_notifier: nwg::NoticeSender,
+        crash_notifier: nwg::NoticeSender,
+        paused: Arc<AtomicBool>,
+        waker: Arc<Waker>,
+        diagnostics: Diagnostics,
+    ) -> Self {
+        Self {
+            locallow,
+            config: Mutex::new(config),
+            config_rx: Mutex::new(config_rx),
+            error_tx,
+            error_notifier,
+            status_tx,
+            status_notifier,
+            crash_notifier,
+            paused,
+            waker,
+            diagnostics,
+            deadline: AtomicU64::new(0),
+            steps: AtomicU64::new(0),
+            generation: AtomicU64::new(0),
+            shutdown: AtomicBool::new(false),
+            busy: Mutex::new(false),
+            idle: Condvar::new(),
+        }
+    }
+
+    /// Asks the worker to stop once done with the logfile at hand and waits up to
+    /// `timeout` for it to get there, so that exiting does not cut an archive short.
+    /// Returns false if it was still busy.
+    pub fn shut_down(&self, timeout: Duration) -> bool {
+        let busy = self.busy.lock().unwrap();
+        self.shutdown.store(true, Ordering::SeqCst);
+        self.waker.wake();
+        let (busy, _) = self
+            .idle
+            .wait_timeout_while(busy, timeout, |&mut busy| busy)
+            .unwrap();
+        !*busy
+    }
+
+    /// Returns false instead if shutting down.
+    fn begin_step(&self) -> bool {
+        let mut busy = self.busy.lock().unwrap();
+        if self.shutdown.load(Ordering::SeqCst) {
+            return false;
+        }
+        *busy = true;
+        true
+    }
+
+    fn end_step(&self) {
+        *self.busy.lock().unwrap() = false;
+        self.idle.notify_all();
+    }
+
+    fn check_in(&self, allowed: Duration) {
+        self.deadline
+            .store(now_secs() + allowed.as_secs(), Ordering::SeqCst);
+    }
+
+    /// Returns false once the UI has gone away. `level` is the notification level from
+    /// which the UI shows it.
+    fn report_error(
+        &self,
+        unrotate: &mut Unrotate,
+        level: NotificationLevel,
+        e: anyhow::Error,
+    ) -> bool {
+        self.diagnostics.log(format_args!("error: {:#}", e));
+        if !unrotate.should_notify(&e) {
+            self.diagnostics
+                .log(format_args!("notification suppressed, notified recently"));
+            return true;
+        }
+        queue_error(&self.error_tx, (e, level), || self.error_notifier.notice())
+    }
+}
+
+/// Hands `error` to the UI, calling `notify` once it is queued, without ever blocking the
+/// worker. Returns false once the UI has gone away.
+fn queue_error(
+    error_tx: &mpsc::SyncSender<(anyhow::Error, NotificationLevel)>,
+    error: (anyhow::Error, NotificationLevel),
+    notify: impl FnOnce(),
+) -> bool {
+    match error_tx.try_send(error) {
+        Ok(()) => {
+            notify();
+            true
+        }
+        // the UI has yet to drain the queue and will notice regardless
+        Err(mpsc::TrySendError::Full(_)) => true,
+        Err(mpsc::TrySendError::Disconnected(_)) => false,
+    }
+}
+
+/// Runs `unrotate` on a worker thread, along with a supervisor that replaces the worker
+/// if it dies or stops checking in.
+pub fn spawn(context: Arc<WorkerContext>, unrotate: Unrotate) {
+    thread::spawn(move || supervise(context, unrotate));
+}
+
+fn start(context: &Arc<WorkerContext>, unrotate: Unrotate) -> JoinHandle<Exit> {
+    let generation = context.generation.load(Ordering::SeqCst);
+    context.check_in(STEP_TIMEOUT);
+    let context = context.clone();
+    thread::spawn(move || run(&context, unrotate, generation))
+}
+
+fn supervise(context: Arc<WorkerContext>, unrotate: Unrotate) {
+    let mut worker = start(&context, unrotate);
+    let mut restarts = 0;
+    let mut steps_at_restart = 0;
+    loop {
+        thread::sleep(SUPERVISE_INTERVAL);
+        let stalled = now_secs() > context.deadline.load(Ordering::SeqCst);
+        if !worker.is_finished() && !stalled {
+            if context.steps.load(Ordering::SeqCst) > steps_at_restart {
+                restarts = 0;
+            }
+            continue;
+        }
+        if worker.is_finished() {
+            match worker.join() {
+                Ok(Exit::Shutdown) => return,
+                Ok(Exit::Superseded) => {}
+                Err(payload) => context
+                    .diagnostics
+                    .log(format_args!("worker died: {}", panic_message(&*payload))),
+            }
+        } else {
+            // a stuck thread cannot be stopped, only left behind
+            context
+                .diagnostics
+                .log(format_args!("worker stopped checking in"));
+            context.generation.fetch_add(1, Ordering::SeqCst);
+        }
+        restarts += 1;
+        if restarts > MAX_RESTARTS {
+            context
+                .diagnostics
+                .log(format_args!("giving up after {} restarts", MAX_RESTARTS));
+            context.crash_notifier.notice();
+            return;
+        }
+        context.diagnostics.log(format_args!(
+            "restarting worker ({}/{})",
+            restarts, MAX_RESTARTS
+        ));
+        steps_at_restart = context.steps.load(Ordering::SeqCst);
+        let config = context.config.lock().unwrap().clone();
+        let unrotate = Unrotate::new(&context.locallow, &config);
+        worker = start(&context, unrotate);
+    }
+}
+
+fn run(context: &WorkerContext, mut unrotate: Unrotate, generation: u64) -> Exit {
+    let (mut interval, mut idle_interval, watch) = {
+        let config = context.config.lock().unwrap();
+        (config.interval(), config.idle_interval(), config.watch)
+    };
+    let wake_grace = (interval * 3).max(MIN_WAKE_GRACE);
+    // falls back to plain polling when the directory cannot be watched
+    let mut watchers = if watch { unrotate.watch() } else { vec![] };
+    // consecutive failed steps, including panics
+    let mut failures = 0;
+    // a manual scan runs even while paused
+    let mut woken = false;
+    // the first time VRChat is seen not running still gets the normal interval, so that
+    // the last session's log is picked up soon after it exits
+    let mut vrchat_was_running = true;
+    loop {
+        if context.generation.load(Ordering::SeqCst) != generation {
+            return Exit::Superseded;
+        }
+        context.check_in(STEP_TIMEOUT);
+        for config in context.config_rx.lock().unwrap().try_iter() {
+            interval = config.interval();
+            idle_interval = config.idle_interval();
+            unrotate.apply_config(&config);
+            *context.config.lock().unwrap() = config;
+        }
+        if woken || !context.paused.load(Ordering::SeqCst) {
+            if !context.begin_step() {
+                return Exit::Shutdown;
+            }
+            let outcome = panic::catch_unwind(AssertUnwindSafe(|| {
+                let mut last_progress: Option<Instant> = None;
+                let result = unrotate.step(|done, total| {
+                    if context.shutdown.load(Ordering::SeqCst) {
+                        return ControlFlow::Break(());
+                    }
+                    if total >= MIN_BACKLOG
+                        && last_progress.is_none_or(|at| at.elapsed() >= PROGRESS_INTERVAL)
+                    {
+                        last_progress = Some(Instant::now());
+                        let status = WorkerStatus::Progress { done, total };
+                        if context.status_tx.send(status).is_ok() {
+                            context.status_notifier.notice();
+                        }
+                    }
+                    ControlFlow::Continue(())
+                });
+                // housekeeping can wait for the next start
+                if context.shutdown.load(Ordering::SeqCst) {
+                    return (result, Ok(()));
+                }
+                (result, unrotate.maintain())
+            }));
+            context.end_step();
+            let (step_result, maintain_result) = match outcome {
+                Ok(results) => results,
+                Err(payload) => {
+                    failures += 1;
+                    context.diagnostics.log(format_args!(
+                        "worker panicked: {}",
+                        panic_message(&*payload)
+                    ));
+                    let delay = backoff(interval, failures);
+                    context.check_in(delay + wake_grace);
+                    woken = context.waker.wait(delay);
+                    continue;
+                }
+            };
+            match step_result {
+                Ok(stats) => {
+                    failures = 0;
+                    context.steps.fetch_add(1, Ordering::SeqCst);
+                    context.diagnostics.log(format_args!(
+                        "scanned {} logfile(s), linked {}",
+                        stats.scanned, stats.linked
+                    ));
+                    let report = StepReport {
+                        finished_at: Local::now(),
+                        linked: stats.linked,
+                    };
+                    if context
+                        .status_tx
+                        .send(WorkerStatus::Finished(report))
+                        .is_ok()
+                    {
+                        context.status_notifier.notice();
+                    }
+                    if !stats
+                        .warnings
+                        .into_iter()
+                        .all(|e| context.report_error(&mut unrotate, NotificationLevel::Normal, e))
+                    {
+                        return Exit::Shutdown;
+                    }
+                }
+                Err(e) => {
+                    failures += 1;
+                    // only the first failure of a streak is worth a notification
+                    if failures > 1 {
+                        context.diagnostics.log(format_args!("error: {:#}", e));
+                    } else if !context.report_error(&mut unrotate, NotificationLevel::ErrorsOnly, e)
+                    {
+                        return Exit::Shutdown;
+                    }
+                }
+            }
+            if let Err(e) = maintain_result {
+                if !context.report_error(&mut unrotate, NotificationLevel::ErrorsOnly, e) {
+                    return Exit::Shutdown;
+                }
+            }
+        }
+        if failures > 0 {
+            let delay = backoff(interval, failures);
+            context.check_in(delay + wake_grace);
+            woken = context.waker.wait(delay);
+            continue;
+        }
+        let vrchat_running = idle_interval <= interval || process::is_running(VRCHAT_EXE);
+        let wait = if vrchat_running || vrchat_was_running {
+            interval
+        } else {
+            idle_interval
+        };
+        vrchat_was_running = vrchat_running;
+        // coalescing may add a little on top of the interval
+        context.check_in(wait + wake_grace);
+        woken = if watchers.is_empty() {
+            context.waker.wait(wait)
+        } else {
+            // the interval still applies as a safety net in case an event is missed
+            match watch::wait_coalesced(&mut watchers, wait, &context.waker) {
+                Ok(wakeup) => wakeup == Wakeup::Woken,
+                Err(e) => {
+                    watchers.clear();
+                    // polling carries on regardless
+                    if !context.report_error(&mut unrotate, NotificationLevel::Normal, e.into()) {
+                        return Exit::Shutdown;
+                    }
+                    false
+                }
+            }
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ERROR_QUEUE_LEN;
+
+    #[test]
+    fn drops_errors_past_a_full_queue() {
+        let (error_tx, error_rx) = mpsc::sync_channel(ERROR_QUEUE_LEN);
+        let mut notices = 0;
+        for i in 0..ERROR_QUEUE_LEN * 3 {
+            let error = (anyhow::anyhow!("error {}", i), NotificationLevel::Normal);
+            assert!(queue_error(&error_tx, error, || notices += 1));
+        }
+        assert_eq!(notices, ERROR_QUEUE_LEN);
+        let queued: Vec<_> = error_rx.try_iter().map(|(e, _)| e.to_string()).collect();
+        assert_eq!(queued.len(), ERROR_QUEUE_LEN);
+        assert_eq!(queued[0], "error 0");
+        // there is room again once the UI has caught up
+        let error = (anyhow::anyhow!("later"), NotificationLevel::Normal);
+        assert!(queue_error(&error_tx, error, || notices += 1));
+        assert_eq!(notices, ERROR_QUEUE_LEN + 1);
+        assert_eq!(error_rx.try_recv().unwrap().0.to_string(), "later");
+    }
+
+    #[test]
+    fn stops_once_the_ui_is_gone() {
+        let (error_tx, error_rx) = mpsc::sync_channel(ERROR_QUEUE_LEN);
+        drop(error_rx);
+        let error = (anyhow::anyhow!("error"), NotificationLevel::Normal);
+        assert!(!queue_error(&error_tx, error, || panic!("notified")));
+    }
+}
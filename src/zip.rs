@@ -0,0 +1,175 @@
+use std::convert::TryFrom;
+use std::io::{self, Read, Write};
+
+use chrono::{Datelike, NaiveDateTime, Timelike};
+use flate2::{write::DeflateEncoder, Compression};
+
+const LOCAL_FILE_HEADER_SIGNATURE: u32 = 0x0403_4b50;
+const DATA_DESCRIPTOR_SIGNATURE: u32 = 0x0807_4b50;
+const CENTRAL_DIRECTORY_SIGNATURE: u32 = 0x0201_4b50;
+const END_OF_CENTRAL_DIRECTORY_SIGNATURE: u32 = 0x0605_4b50;
+// sizes follow the data in a descriptor, and names are UTF-8
+const FLAGS: u16 = 0x0008 | 0x0800;
+const METHOD_DEFLATE: u16 = 8;
+const VERSION: u16 = 20;
+
+struct CountingWriter<W> {
+    inner: W,
+    count: u64,
+}
+
+impl<W: Write> Write for CountingWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let written = self.inner.write(buf)?;
+        self.count += written as u64;
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+struct CentralEntry {
+    name: String,
+    dos_time: u16,
+    dos_date: u16,
+    crc: u32,
+    compressed_size: u32,
+    size: u32,
+    offset: u32,
+}
+
+/// Writes a zip archive front to back, so entries never need to be held in memory.
+///
+/// Zip64 is not supported; archives and entries are limited to 4 GiB.
+pub struct ZipWriter<W: Write> {
+    writer: CountingWriter<W>,
+    entries: Vec<CentralEntry>,
+}
+
+fn too_large() -> io::Error {
+    io::Error::other("too large for a zip archive")
+}
+
+fn to_u32(n: u64) -> io::Result<u32> {
+    u32::try_from(n).map_err(|_| too_large())
+}
+
+fn dos_date_time(modified: NaiveDateTime) -> (u16, u16) {
+    // DOS timestamps cannot represent anything before 1980
+    let year = modified.year().clamp(1980, 2107) as u16;
+    let date = ((year - 1980) << 9) | ((modified.month() as u16) << 5) | modified.day() as u16;
+    let time = ((modified.hour() as u16) << 11)
+        | ((modified.minute() as u16) << 5)
+        | (modified.second() as u16 / 2);
+    (date, time)
+}
+
+impl<W: Write> ZipWriter<W> {
+    pub fn new(writer: W) -> Self {
+        Self {
+            writer: CountingWriter {
+                inner: writer,
+                count: 0,
+            },
+            entries: vec![],
+        }
+    }
+
+    /// Deflates everything `reader` yields into a new entry named `name`.
+    pub fn add_file(
+        &mut self,
+        name: &str,
+        modified: NaiveDateTime,
+        reader: &mut impl Read,
+    ) -> io::Result<()> {
+        let offset = to_u32(self.writer.count)?;
+        let (dos_date, dos_time) = dos_date_time(modified);
+        let name_len = u16::try_from(name.len()).map_err(|_| too_large())?;
+        let w = &mut self.writer;
+        w.write_all(&LOCAL_FILE_HEADER_SIGNATURE.to_le_bytes())?;
+        w.write_all(&VERSION.to_le_bytes())?;
+        w.write_all(&FLAGS.to_le_bytes())?;
+        w.write_all(&METHOD_DEFLATE.to_le_bytes())?;
+        w.write_all(&dos_time.to_le_bytes())?;
+        w.write_all(&dos_date.to_le_bytes())?;
+        // crc and sizes, deferred to the data descriptor
+        w.write_all(&[0; 12])?;
+        w.write_all(&name_len.to_le_bytes())?;
+        w.write_all(&0u16.to_le_bytes())?;
+        w.write_all(name.as_bytes())?;
+
+        let data_start = w.count;
+        let mut hasher = crc32fast::Hasher::new();
+        let mut size = 0u64;
+        let mut encoder = DeflateEncoder::new(&mut *w, Compression::default());
+        let mut buf = vec![0; 64 * 1024];
+        loop {
+            let n = match reader.read(&mut buf) {
+                Ok(0) => break,
+                Ok(n) => n,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(e) => return Err(e),
+            };
+            hasher.update(&buf[..n]);
+            encoder.write_all(&buf[..n])?;
+            size += n as u64;
+        }
+        encoder.finish()?;
+        let crc = hasher.finalize();
+        let compressed_size = to_u32(w.count - data_start)?;
+        let size = to_u32(size)?;
+        w.write_all(&DATA_DESCRIPTOR_SIGNATURE.to_le_bytes())?;
+        w.write_all(&crc.to_le_bytes())?;
+        w.write_all(&compressed_size.to_le_bytes())?;
+        w.write_all(&size.to_le_bytes())?;
+
+        self.entries.push(CentralEntry {
+            name: name.to_owned(),
+            dos_time,
+            dos_date,
+            crc,
+            compressed_size,
+            size,
+            offset,
+        });
+        Ok(())
+    }
+
+    /// Writes the central directory and returns the underlying writer.
+    pub fn finish(mut self) -> io::Result<W> {
+        let entry_count = u16::try_from(self.entries.len()).map_err(|_| too_large())?;
+        let directory_offset = to_u32(self.writer.count)?;
+        let w = &mut self.writer;
+        for entry in &self.entries {
+            w.write_all(&CENTRAL_DIRECTORY_SIGNATURE.to_le_bytes())?;
+            w.write_all(&VERSION.to_le_bytes())?;
+            w.write_all(&VERSION.to_le_bytes())?;
+            w.write_all(&FLAGS.to_le_bytes())?;
+            w.write_all(&METHOD_DEFLATE.to_le_bytes())?;
+            w.write_all(&entry.dos_time.to_le_bytes())?;
+            w.write_all(&entry.dos_date.to_le_bytes())?;
+            w.write_all(&entry.crc.to_le_bytes())?;
+            w.write_all(&entry.compressed_size.to_le_bytes())?;
+            w.write_all(&entry.size.to_le_bytes())?;
+            w.write_all(&(entry.name.len() as u16).to_le_bytes())?;
+            // extra field, comment, disk number, internal and external attributes
+            w.write_all(&[0; 12])?;
+            w.write_all(&entry.offset.to_le_bytes())?;
+            w.write_all(entry.name.as_bytes())?;
+        }
+        let directory_size = to_u32(w.count - u64::from(directory_offset))?;
+        w.write_all(&END_OF_CENTRAL_DIRECTORY_SIGNATURE.to_le_bytes())?;
+        // this disk and the disk the directory starts on
+        w.write_all(&[0; 4])?;
+        w.write_all(&entry_count.to_le_bytes())?;
+        w.write_all(&entry_count.to_le_bytes())?;
+        w.write_all(&directory_size.to_le_bytes())?;
+        w.write_all(&directory_offset.to_le_bytes())?;
+        // comment length
+        w.write_all(&0u16.to_le_bytes())?;
+        w.flush()?;
+        Ok(self.writer.inner)
+    }
+}